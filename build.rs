@@ -78,10 +78,10 @@ fn get_version() -> String {
 }
 
 fn get_buildtimestamp() -> String {
-    return chrono::Local::now()
+    chrono::Local::now()
         .naive_local()
         .format("%Y-%m-%d %H:%M:%S")
-        .to_string();
+        .to_string()
 }
 
 fn create_dir(dir_name: &str) {
@@ -21,31 +21,181 @@ Register-ArgumentCompleter -Native -CommandName 'blaze' -ScriptBlock {
 
     $completions = @(switch ($command) {
         'blaze' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
             [CompletionResult]::new('install', 'install', [CompletionResultType]::ParameterValue, 'install a new NodeJS package')
+            [CompletionResult]::new('update', 'update', [CompletionResultType]::ParameterValue, 'update packages to the newest versions allowed by their ranges')
+            [CompletionResult]::new('prune', 'prune', [CompletionResultType]::ParameterValue, 'remove packages from node_modules that are no longer depended on')
+            [CompletionResult]::new('dedupe', 'dedupe', [CompletionResultType]::ParameterValue, 'remove duplicate copies of packages by sharing versions between dependents')
+            [CompletionResult]::new('outdated', 'outdated', [CompletionResultType]::ParameterValue, 'list dependencies that have newer versions')
+            [CompletionResult]::new('why', 'why', [CompletionResultType]::ParameterValue, 'explain why a package is installed')
+            [CompletionResult]::new('list', 'list', [CompletionResultType]::ParameterValue, 'print the tree of installed dependencies')
+            [CompletionResult]::new('run', 'run', [CompletionResultType]::ParameterValue, 'run a script defined in package.json')
+            [CompletionResult]::new('test', 'test', [CompletionResultType]::ParameterValue, 'run the test script')
+            [CompletionResult]::new('start', 'start', [CompletionResultType]::ParameterValue, 'run the start script')
+            [CompletionResult]::new('exec', 'exec', [CompletionResultType]::ParameterValue, 'run a binary from a package, installing the package temporarily when needed')
+            [CompletionResult]::new('lock', 'lock', [CompletionResultType]::ParameterValue, 'work with the lockfile')
             [CompletionResult]::new('init', 'init', [CompletionResultType]::ParameterValue, 'initialize a new NodeJS project')
             [CompletionResult]::new('version', 'version', [CompletionResultType]::ParameterValue, 'Print the version')
             [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
             break
         }
         'blaze;install' {
+            [CompletionResult]::new('-w', 'w', [CompletionResultType]::ParameterName, 'only install for the workspaces matching this name or path')
+            [CompletionResult]::new('--filter', 'filter', [CompletionResultType]::ParameterName, 'only install for the workspaces matching this name or path')
+            [CompletionResult]::new('--network-concurrency', 'network-concurrency', [CompletionResultType]::ParameterName, 'the maximum number of registry requests and downloads at once')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--ignore-scripts', 'ignore-scripts', [CompletionResultType]::ParameterName, 'do not run the lifecycle scripts of the project')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
             break
         }
+        'blaze;update' {
+            [CompletionResult]::new('--network-concurrency', 'network-concurrency', [CompletionResultType]::ParameterName, 'the maximum number of registry requests and downloads at once')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--latest', 'latest', [CompletionResultType]::ParameterName, 'bump the ranges in package.json to the latest versions')
+            [CompletionResult]::new('-i', 'i', [CompletionResultType]::ParameterName, 'choose the packages to update interactively')
+            [CompletionResult]::new('--interactive', 'interactive', [CompletionResultType]::ParameterName, 'choose the packages to update interactively')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;prune' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--dry-run', 'dry-run', [CompletionResultType]::ParameterName, 'list what would be removed without removing it')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;dedupe' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;outdated' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--json', 'json', [CompletionResultType]::ParameterName, 'print the report as JSON')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;why' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;list' {
+            [CompletionResult]::new('--depth', 'depth', [CompletionResultType]::ParameterName, 'how deep into the tree to print')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--json', 'json', [CompletionResultType]::ParameterName, 'print the tree as JSON')
+            [CompletionResult]::new('--prod', 'prod', [CompletionResultType]::ParameterName, 'only show dependencies')
+            [CompletionResult]::new('--dev', 'dev', [CompletionResultType]::ParameterName, 'only show devDependencies')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;run' {
+            [CompletionResult]::new('-w', 'w', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('--filter', 'filter', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;test' {
+            [CompletionResult]::new('-w', 'w', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('--filter', 'filter', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;start' {
+            [CompletionResult]::new('-w', 'w', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('--filter', 'filter', [CompletionResultType]::ParameterName, 'run the script in the workspaces matching this name or path')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;exec' {
+            [CompletionResult]::new('-p', 'p', [CompletionResultType]::ParameterName, 'the package providing the binary, defaults to the command')
+            [CompletionResult]::new('--package', 'package', [CompletionResultType]::ParameterName, 'the package providing the binary, defaults to the command')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            break
+        }
+        'blaze;lock' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
+            [CompletionResult]::new('export', 'export', [CompletionResultType]::ParameterValue, 'write the lockfile in the format of another package manager')
+            [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
+            break
+        }
+        'blaze;lock;export' {
+            [CompletionResult]::new('--format', 'format', [CompletionResultType]::ParameterName, 'the lockfile format to write')
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            break
+        }
+        'blaze;lock;help' {
+            [CompletionResult]::new('export', 'export', [CompletionResultType]::ParameterValue, 'write the lockfile in the format of another package manager')
+            [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
+            break
+        }
+        'blaze;lock;help;export' {
+            break
+        }
+        'blaze;lock;help;help' {
+            break
+        }
         'blaze;init' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
             break
         }
         'blaze;version' {
+            [CompletionResult]::new('-C', 'C ', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
+            [CompletionResult]::new('--prefix', 'prefix', [CompletionResultType]::ParameterName, 'run as if blaze was started in this directory')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help')
             break
         }
         'blaze;help' {
             [CompletionResult]::new('install', 'install', [CompletionResultType]::ParameterValue, 'install a new NodeJS package')
+            [CompletionResult]::new('update', 'update', [CompletionResultType]::ParameterValue, 'update packages to the newest versions allowed by their ranges')
+            [CompletionResult]::new('prune', 'prune', [CompletionResultType]::ParameterValue, 'remove packages from node_modules that are no longer depended on')
+            [CompletionResult]::new('dedupe', 'dedupe', [CompletionResultType]::ParameterValue, 'remove duplicate copies of packages by sharing versions between dependents')
+            [CompletionResult]::new('outdated', 'outdated', [CompletionResultType]::ParameterValue, 'list dependencies that have newer versions')
+            [CompletionResult]::new('why', 'why', [CompletionResultType]::ParameterValue, 'explain why a package is installed')
+            [CompletionResult]::new('list', 'list', [CompletionResultType]::ParameterValue, 'print the tree of installed dependencies')
+            [CompletionResult]::new('run', 'run', [CompletionResultType]::ParameterValue, 'run a script defined in package.json')
+            [CompletionResult]::new('test', 'test', [CompletionResultType]::ParameterValue, 'run the test script')
+            [CompletionResult]::new('start', 'start', [CompletionResultType]::ParameterValue, 'run the start script')
+            [CompletionResult]::new('exec', 'exec', [CompletionResultType]::ParameterValue, 'run a binary from a package, installing the package temporarily when needed')
+            [CompletionResult]::new('lock', 'lock', [CompletionResultType]::ParameterValue, 'work with the lockfile')
             [CompletionResult]::new('init', 'init', [CompletionResultType]::ParameterValue, 'initialize a new NodeJS project')
             [CompletionResult]::new('version', 'version', [CompletionResultType]::ParameterValue, 'Print the version')
             [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
@@ -54,6 +204,43 @@ Register-ArgumentCompleter -Native -CommandName 'blaze' -ScriptBlock {
         'blaze;help;install' {
             break
         }
+        'blaze;help;update' {
+            break
+        }
+        'blaze;help;prune' {
+            break
+        }
+        'blaze;help;dedupe' {
+            break
+        }
+        'blaze;help;outdated' {
+            break
+        }
+        'blaze;help;why' {
+            break
+        }
+        'blaze;help;list' {
+            break
+        }
+        'blaze;help;run' {
+            break
+        }
+        'blaze;help;test' {
+            break
+        }
+        'blaze;help;start' {
+            break
+        }
+        'blaze;help;exec' {
+            break
+        }
+        'blaze;help;lock' {
+            [CompletionResult]::new('export', 'export', [CompletionResultType]::ParameterValue, 'write the lockfile in the format of another package manager')
+            break
+        }
+        'blaze;help;lock;export' {
+            break
+        }
         'blaze;help;init' {
             break
         }
@@ -1,12 +1,84 @@
+complete -c blaze -n "__fish_use_subcommand" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
 complete -c blaze -n "__fish_use_subcommand" -s h -l help -d 'Print help'
 complete -c blaze -n "__fish_use_subcommand" -f -a "install" -d 'install a new NodeJS package'
+complete -c blaze -n "__fish_use_subcommand" -f -a "update" -d 'update packages to the newest versions allowed by their ranges'
+complete -c blaze -n "__fish_use_subcommand" -f -a "prune" -d 'remove packages from node_modules that are no longer depended on'
+complete -c blaze -n "__fish_use_subcommand" -f -a "dedupe" -d 'remove duplicate copies of packages by sharing versions between dependents'
+complete -c blaze -n "__fish_use_subcommand" -f -a "outdated" -d 'list dependencies that have newer versions'
+complete -c blaze -n "__fish_use_subcommand" -f -a "why" -d 'explain why a package is installed'
+complete -c blaze -n "__fish_use_subcommand" -f -a "list" -d 'print the tree of installed dependencies'
+complete -c blaze -n "__fish_use_subcommand" -f -a "run" -d 'run a script defined in package.json'
+complete -c blaze -n "__fish_use_subcommand" -f -a "test" -d 'run the test script'
+complete -c blaze -n "__fish_use_subcommand" -f -a "start" -d 'run the start script'
+complete -c blaze -n "__fish_use_subcommand" -f -a "exec" -d 'run a binary from a package, installing the package temporarily when needed'
+complete -c blaze -n "__fish_use_subcommand" -f -a "lock" -d 'work with the lockfile'
 complete -c blaze -n "__fish_use_subcommand" -f -a "init" -d 'initialize a new NodeJS project'
 complete -c blaze -n "__fish_use_subcommand" -f -a "version" -d 'Print the version'
 complete -c blaze -n "__fish_use_subcommand" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c blaze -n "__fish_seen_subcommand_from install" -s w -l filter -d 'only install for the workspaces matching this name or path' -r
+complete -c blaze -n "__fish_seen_subcommand_from install" -l network-concurrency -d 'the maximum number of registry requests and downloads at once' -r
+complete -c blaze -n "__fish_seen_subcommand_from install" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from install" -l ignore-scripts -d 'do not run the lifecycle scripts of the project'
 complete -c blaze -n "__fish_seen_subcommand_from install" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from update" -l network-concurrency -d 'the maximum number of registry requests and downloads at once' -r
+complete -c blaze -n "__fish_seen_subcommand_from update" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from update" -l latest -d 'bump the ranges in package.json to the latest versions'
+complete -c blaze -n "__fish_seen_subcommand_from update" -s i -l interactive -d 'choose the packages to update interactively'
+complete -c blaze -n "__fish_seen_subcommand_from update" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from prune" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from prune" -l dry-run -d 'list what would be removed without removing it'
+complete -c blaze -n "__fish_seen_subcommand_from prune" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from dedupe" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from dedupe" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from outdated" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from outdated" -l json -d 'print the report as JSON'
+complete -c blaze -n "__fish_seen_subcommand_from outdated" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from why" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from why" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from list" -l depth -d 'how deep into the tree to print' -r
+complete -c blaze -n "__fish_seen_subcommand_from list" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from list" -l json -d 'print the tree as JSON'
+complete -c blaze -n "__fish_seen_subcommand_from list" -l prod -d 'only show dependencies'
+complete -c blaze -n "__fish_seen_subcommand_from list" -l dev -d 'only show devDependencies'
+complete -c blaze -n "__fish_seen_subcommand_from list" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from run" -s w -l filter -d 'run the script in the workspaces matching this name or path' -r
+complete -c blaze -n "__fish_seen_subcommand_from run" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from run" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from test" -s w -l filter -d 'run the script in the workspaces matching this name or path' -r
+complete -c blaze -n "__fish_seen_subcommand_from test" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from test" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from start" -s w -l filter -d 'run the script in the workspaces matching this name or path' -r
+complete -c blaze -n "__fish_seen_subcommand_from start" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from start" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from exec" -s p -l package -d 'the package providing the binary, defaults to the command' -r
+complete -c blaze -n "__fish_seen_subcommand_from exec" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from exec" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -f -a "export" -d 'write the lockfile in the format of another package manager'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and __fish_seen_subcommand_from export" -l format -d 'the lockfile format to write' -r -f -a "{npm	package-lock.json with lockfileVersion 3}"
+complete -c blaze -n "__fish_seen_subcommand_from lock; and __fish_seen_subcommand_from export" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
+complete -c blaze -n "__fish_seen_subcommand_from lock; and __fish_seen_subcommand_from export" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and __fish_seen_subcommand_from help; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -f -a "export" -d 'write the lockfile in the format of another package manager'
+complete -c blaze -n "__fish_seen_subcommand_from lock; and __fish_seen_subcommand_from help; and not __fish_seen_subcommand_from export; and not __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c blaze -n "__fish_seen_subcommand_from init" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
 complete -c blaze -n "__fish_seen_subcommand_from init" -s h -l help -d 'Print help'
+complete -c blaze -n "__fish_seen_subcommand_from version" -s C -l prefix -d 'run as if blaze was started in this directory' -r -F
 complete -c blaze -n "__fish_seen_subcommand_from version" -s h -l help -d 'Print help'
-complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "install" -d 'install a new NodeJS package'
-complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "init" -d 'initialize a new NodeJS project'
-complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "version" -d 'Print the version'
-complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "install" -d 'install a new NodeJS package'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "update" -d 'update packages to the newest versions allowed by their ranges'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "prune" -d 'remove packages from node_modules that are no longer depended on'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "dedupe" -d 'remove duplicate copies of packages by sharing versions between dependents'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "outdated" -d 'list dependencies that have newer versions'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "why" -d 'explain why a package is installed'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "list" -d 'print the tree of installed dependencies'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "run" -d 'run a script defined in package.json'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "test" -d 'run the test script'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "start" -d 'run the start script'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "exec" -d 'run a binary from a package, installing the package temporarily when needed'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "lock" -d 'work with the lockfile'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "init" -d 'initialize a new NodeJS project'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "version" -d 'Print the version'
+complete -c blaze -n "__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from install; and not __fish_seen_subcommand_from update; and not __fish_seen_subcommand_from prune; and not __fish_seen_subcommand_from dedupe; and not __fish_seen_subcommand_from outdated; and not __fish_seen_subcommand_from why; and not __fish_seen_subcommand_from list; and not __fish_seen_subcommand_from run; and not __fish_seen_subcommand_from test; and not __fish_seen_subcommand_from start; and not __fish_seen_subcommand_from exec; and not __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from init; and not __fish_seen_subcommand_from version; and not __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c blaze -n "__fish_seen_subcommand_from help; and __fish_seen_subcommand_from lock; and not __fish_seen_subcommand_from export" -f -a "export" -d 'write the lockfile in the format of another package manager'
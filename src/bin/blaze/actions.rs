@@ -1,58 +1,512 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use blaze::dependencies::{get_latest_version, Dependencies};
+use blaze::concurrency::{Concurrency, DEFAULT_NETWORK_CONCURRENCY};
+use blaze::dependencies::{get_latest_version, Dependencies, INSTALL_SCRIPTS, PREINSTALL_SCRIPTS};
+use blaze::exec::exec;
+use blaze::export::{write_package_lock, PACKAGE_LOCK_NAME};
+use blaze::list::{build_dependency_tree, DependencyNode, ListOptions};
+use blaze::lockfile::Lockfile;
+use blaze::npmrc::Npmrc;
+use blaze::outdated::{find_outdated_dependencies, OutdatedDependency};
+use blaze::project::Project;
+use blaze::registry::default_registry;
 use colored::Colorize;
-use inquire::Text;
+use indicatif::HumanBytes;
+use inquire::{MultiSelect, Text};
 use serde::{Deserialize, Serialize};
 use serde_json::to_string_pretty;
 
-use crate::cli::Command;
+use blaze::scripts::Scripts;
+use blaze::specifier::Specifier;
+use blaze::utils::{read_package_json, split_package_spec};
+use blaze::version_range::VersionRange;
+use blaze::workspaces::{filter_workspaces, find_workspaces};
+
+use crate::cli::{Command, LockCommand, LockFormat};
 use crate::error;
+use crate::progress::ProgressReporter;
+
+/// The status `blaze outdated` exits with when it found outdated dependencies, to tell them
+/// apart from the command failing, which exits with 1
+const OUTDATED_EXIT_CODE: i32 = 2;
 
 impl Command {
-    pub async fn run(&self) {
+    pub async fn run(&self, project: &Project) {
+        let registry = default_registry();
+        let reporter = Arc::new(ProgressReporter::new());
+
         match self {
-            Command::Install { package_names } => {
-                let mut dependencies;
+            Command::Install {
+                package_names,
+                filter,
+                network_concurrency,
+                ignore_scripts,
+            } => {
+                let workspaces = match find_workspaces(&project.root) {
+                    Ok(workspaces) => workspaces,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+
+                let filtered_workspaces = if filter.is_empty() {
+                    Vec::new()
+                } else {
+                    match filter_workspaces(&workspaces, filter) {
+                        Ok(filtered_workspaces) => filtered_workspaces,
+                        Err(error) => {
+                            error::report(&error);
+                            std::process::exit(1);
+                        }
+                    }
+                };
 
                 if !package_names.is_empty() {
-                    dependencies = Dependencies::default();
+                    // new packages go to the filtered workspaces, or to the package blaze was
+                    // started in
+                    let package_json_paths: Vec<PathBuf> = if filter.is_empty() {
+                        vec![project.package_directory.join("package.json")]
+                    } else {
+                        filtered_workspaces
+                            .iter()
+                            .map(|workspace| workspace.package_json_path())
+                            .collect()
+                    };
+
+                    let mut versions = Vec::new();
 
                     for package_name in package_names {
-                        let version = match get_latest_version(package_name).await {
-                            Ok(version) => version,
-                            Err(error) => {
-                                error::print_error(&error);
-                                return;
-                            }
-                        };
-
-                        dependencies
-                            .dependencies
-                            .insert(package_name.to_string(), version);
-
-                        if let Err(e) = dependencies.write_dependencies_to_package_json() {
+                        let version =
+                            match get_latest_version(registry.as_ref(), package_name).await {
+                                Ok(version) => version,
+                                Err(error) => {
+                                    error::report(&error);
+                                    std::process::exit(1);
+                                }
+                            };
+
+                        versions.push((package_name.to_string(), version));
+                    }
+
+                    for package_json_path in package_json_paths {
+                        let mut dependencies =
+                            Dependencies::from_package_json_at(&package_json_path)
+                                .unwrap_or_default();
+
+                        dependencies.dependencies.extend(versions.clone());
+
+                        if let Err(e) =
+                            dependencies.write_dependencies_to_package_json_at(&package_json_path)
+                        {
+                            error::report(&e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+
+                // every workspace is still resolved and locked, since they share `blaze.lock`,
+                // but only the dependencies of the filtered ones are installed
+                let mut dependencies = load_dependencies(project);
+                dependencies.concurrency = concurrency(project, *network_concurrency);
+
+                if !filter.is_empty() {
+                    if let Err(e) =
+                        dependencies.select_workspaces(&filtered_workspaces, &workspaces)
+                    {
+                        error::report(&e);
+                        std::process::exit(1);
+                    }
+                }
+
+                if !ignore_scripts {
+                    if let Err(e) =
+                        dependencies.run_install_scripts(&PREINSTALL_SCRIPTS, reporter.clone())
+                    {
+                        error::report(&e);
+                        std::process::exit(1);
+                    }
+                }
+
+                if let Err(e) = dependencies.download_dependencies(reporter.clone()).await {
+                    error::report(&e);
+                    std::process::exit(1);
+                };
+
+                if !ignore_scripts {
+                    if let Err(e) =
+                        dependencies.run_install_scripts(&INSTALL_SCRIPTS, reporter.clone())
+                    {
+                        error::report(&e);
+                        std::process::exit(1);
+                    }
+                }
+
+                reporter.finish();
+            }
+            Command::Update {
+                package_names,
+                latest,
+                interactive,
+                network_concurrency,
+            } => {
+                let mut dependencies = load_dependencies(project);
+                let root_dependencies = dependencies.root_dependencies();
+
+                for package_name in package_names {
+                    if !root_dependencies.contains_key(package_name) {
+                        error::print_error(&format!(
+                            "{} is not a dependency of this project",
+                            package_name
+                        ));
+                        std::process::exit(1);
+                    }
+                }
+
+                let mut package_names = package_names.clone();
+
+                if *interactive || *latest {
+                    let candidates: Vec<String> = if package_names.is_empty() {
+                        root_dependencies
+                            .iter()
+                            .filter(|(name, range)| {
+                                Specifier::parse(range).registry_package(name).is_some()
+                            })
+                            .map(|(name, _)| name.clone())
+                            .collect()
+                    } else {
+                        package_names.clone()
+                    };
+
+                    let mut choices = Vec::new();
+
+                    for package_name in candidates {
+                        // an `npm:` alias moves to the latest version of the package it points at
+                        let package = Specifier::parse(&root_dependencies[&package_name])
+                            .registry_package(&package_name)
+                            .map_or_else(|| package_name.clone(), |(package, _)| package);
+
+                        let latest_version =
+                            match get_latest_version(registry.as_ref(), &package).await {
+                                Ok(version) => version,
+                                Err(error) => {
+                                    error::report(&error);
+                                    std::process::exit(1);
+                                }
+                            };
+
+                        choices.push(UpdateChoice {
+                            range: root_dependencies[&package_name].clone(),
+                            name: package_name,
+                            package,
+                            latest_version,
+                        });
+                    }
+
+                    if *interactive {
+                        choices =
+                            match MultiSelect::new("Select packages to update", choices).prompt() {
+                                Ok(choices) => choices,
+                                Err(_) => {
+                                    error::print_error(
+                                        "An unexpected error occurred while selecting packages",
+                                    );
+                                    std::process::exit(1);
+                                }
+                            };
+
+                        if choices.is_empty() {
+                            println!("Nothing to update");
+                            return;
+                        }
+                    }
+
+                    package_names = choices.iter().map(|choice| choice.name.clone()).collect();
+
+                    if *latest {
+                        if let Err(e) = bump_ranges(project, &choices) {
+                            error::report(&e);
+                            std::process::exit(1);
+                        }
+
+                        dependencies = load_dependencies(project);
+                    }
+                }
+
+                dependencies.concurrency = concurrency(project, *network_concurrency);
+
+                if let Err(e) = dependencies
+                    .update_dependencies(&package_names, reporter.clone())
+                    .await
+                {
+                    error::report(&e);
+                    std::process::exit(1);
+                }
+
+                reporter.finish();
+            }
+            Command::Prune { dry_run } => {
+                let mut dependencies = match Dependencies::from_package_json(&project.root) {
+                    Ok(dependencies) => dependencies,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+
+                // every workspace stays linked
+                let workspaces = match find_workspaces(&project.root) {
+                    Ok(workspaces) => workspaces,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+                if let Err(error) = dependencies.add_workspaces(&[], &workspaces) {
+                    error::report(&error);
+                    std::process::exit(1);
+                }
+
+                let extraneous = match dependencies.prune(*dry_run) {
+                    Ok(extraneous) => extraneous,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+
+                if extraneous.is_empty() {
+                    println!("Nothing to prune");
+                    return;
+                }
+
+                let verb = if *dry_run { "Would remove" } else { "Removed" };
+                for entry in &extraneous {
+                    println!(
+                        "{} {}",
+                        verb,
+                        Path::new("node_modules").join(entry.path()).display()
+                    );
+                }
+            }
+            Command::Dedupe => {
+                let mut dependencies = load_dependencies(project);
+                dependencies.concurrency = concurrency(project, None);
+
+                let report = match dependencies.dedupe(reporter).await {
+                    Ok(report) => report,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+
+                if report.duplicates_removed == 0 {
+                    println!("No duplicate packages to remove");
+                    return;
+                }
+
+                for (path, old_version, new_version) in &report.changed {
+                    println!("{} {} -> {}", path, old_version, new_version.green());
+                }
+
+                println!(
+                    "Removed {} {}, saving {}",
+                    report.duplicates_removed,
+                    if report.duplicates_removed == 1 {
+                        "duplicate"
+                    } else {
+                        "duplicates"
+                    },
+                    HumanBytes(report.bytes_saved)
+                );
+            }
+            Command::Outdated { json } => {
+                let mut dependencies = load_dependencies(project);
+                dependencies.concurrency = concurrency(project, None);
+
+                let outdated = match find_outdated_dependencies(&dependencies).await {
+                    Ok(outdated) => outdated,
+                    Err(error) => {
+                        error::report(&error);
+                        std::process::exit(1);
+                    }
+                };
+
+                if *json {
+                    let report: std::collections::BTreeMap<_, _> = outdated
+                        .iter()
+                        .map(|dependency| (dependency.name.clone(), dependency))
+                        .collect();
+
+                    match to_string_pretty(&report) {
+                        Ok(json) => println!("{}", json),
+                        Err(e) => {
+                            error::print_error(&e.to_string());
+                            std::process::exit(1);
+                        }
+                    }
+                } else if !outdated.is_empty() {
+                    print_outdated_table(&outdated);
+                }
+
+                if !outdated.is_empty() {
+                    std::process::exit(OUTDATED_EXIT_CODE);
+                }
+            }
+            Command::Why { package } => {
+                let lockfile = match Lockfile::read(&project.root) {
+                    Some(lockfile) => lockfile,
+                    None => {
+                        error::report(&blaze::Error::MissingLockfile);
+                        std::process::exit(1);
+                    }
+                };
+
+                let (package_name, version) = split_package_spec(package);
+                let paths = lockfile.dependency_paths(package_name, version);
+
+                if paths.is_empty() {
+                    error::report(&blaze::Error::NotInstalled {
+                        package: package.clone(),
+                    });
+                    std::process::exit(1);
+                }
+
+                let project_name = read_package_json(&project.root)
+                    .ok()
+                    .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+                    .and_then(|package_json| package_json["name"].as_str().map(String::from))
+                    .unwrap_or_else(|| "(root)".to_string());
+
+                for path in paths {
+                    let path: Vec<String> = path
+                        .iter()
+                        .map(|(name, version)| format!("{}@{}", name, version))
+                        .collect();
+
+                    println!("{} > {}", project_name.dimmed(), path.join(" > ").green());
+                }
+            }
+            Command::List {
+                depth,
+                json,
+                prod,
+                dev,
+            } => {
+                let dependencies = load_dependencies(project);
+
+                let tree = build_dependency_tree(
+                    &dependencies,
+                    ListOptions {
+                        depth: *depth,
+                        prod: *prod,
+                        dev: *dev,
+                    },
+                );
+
+                if *json {
+                    match to_string_pretty(&tree) {
+                        Ok(json) => println!("{}", json),
+                        Err(e) => {
                             error::print_error(&e.to_string());
                             return;
                         }
                     }
                 } else {
-                    dependencies = match Dependencies::from_package_json() {
-                        Ok(dependencies) => dependencies,
+                    let root = match (&tree.name, &tree.version) {
+                        (Some(name), Some(version)) => format!("{}@{}", name, version),
+                        (Some(name), None) => name.clone(),
+                        _ => "(root)".to_string(),
+                    };
+
+                    println!("{}", root);
+                    print_dependency_nodes(&tree.dependencies, "");
+                }
+
+                if tree.has_problems() {
+                    std::process::exit(1);
+                }
+            }
+            Command::Run {
+                script,
+                args,
+                filter,
+            } => match script {
+                Some(script) => run_script(project, script, args, filter),
+                None => {
+                    let scripts = match Scripts::from_directory(&project.package_directory) {
+                        Ok(scripts) => scripts,
                         Err(error) => {
-                            error::print_error(&error.to_string());
+                            error::report(&error);
                             return;
                         }
                     };
+
+                    if scripts.scripts.is_empty() {
+                        println!("No scripts defined in package.json");
+                        return;
+                    }
+
+                    println!("Available scripts:");
+                    for (name, script) in &scripts.scripts {
+                        println!("  {}", name.green());
+                        println!("    {}", script.dimmed());
+                    }
+                }
+            },
+            Command::Test { args, filter } => run_script(project, "test", args, filter),
+            Command::Start { args, filter } => run_script(project, "start", args, filter),
+            Command::Exec {
+                package,
+                command,
+                args,
+            } => match exec(
+                &project.package_directory,
+                Dependencies::default()
+                    .with_registry(registry)
+                    .with_concurrency(concurrency(project, None)),
+                reporter,
+                command,
+                package.as_deref(),
+                args,
+            )
+            .await
+            {
+                Ok(0) => (),
+                Ok(code) => std::process::exit(code),
+                Err(error) => {
+                    error::report(&error);
+                    std::process::exit(1);
                 }
+            },
+            Command::Lock { command } => match command {
+                LockCommand::Export { format } => {
+                    let lockfile = match Lockfile::read(&project.root) {
+                        Some(lockfile) => lockfile,
+                        None => {
+                            error::print_error("No blaze.lock found, run `blaze install` first");
+                            std::process::exit(1);
+                        }
+                    };
 
-                if let Err(e) = dependencies.download_dependencies().await {
-                    error::print_error(&e);
-                };
-            }
+                    let result = match format {
+                        LockFormat::Npm => write_package_lock(&lockfile, &project.root),
+                    };
+
+                    match result {
+                        Ok(_) => println!("Wrote {}", PACKAGE_LOCK_NAME),
+                        Err(error) => {
+                            error::report(&error);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            },
             Command::Init {} => {
                 let project_name = match Text::new("Project Name")
                     .with_default("my-amazing-project")
@@ -235,3 +689,306 @@ impl Command {
         }
     }
 }
+
+/// Reads the dependencies of the project along with the ones of every workspace, the way an
+/// install sees them
+fn load_dependencies(project: &Project) -> Dependencies {
+    let result = Dependencies::from_package_json(&project.root).and_then(|mut dependencies| {
+        let workspaces = find_workspaces(&project.root)?;
+        dependencies.add_workspaces(&workspaces, &workspaces)?;
+        Ok(dependencies)
+    });
+
+    match result {
+        Ok(dependencies) => dependencies,
+        Err(error) => {
+            error::report(&error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The limits for an install: `--network-concurrency` wins over `maxsockets` in `.npmrc`
+fn concurrency(project: &Project, network_concurrency: Option<usize>) -> Concurrency {
+    let network = network_concurrency
+        .or_else(|| Npmrc::load(&project.root).maxsockets())
+        .unwrap_or(DEFAULT_NETWORK_CONCURRENCY);
+
+    Concurrency::with_network_limit(network)
+}
+
+/// Runs a script in the package blaze was started in, or in every workspace matched by `filter`
+fn run_script(project: &Project, name: &str, args: &[String], filter: &[String]) {
+    let directories: Vec<PathBuf> = if filter.is_empty() {
+        vec![project.package_directory.clone()]
+    } else {
+        let workspaces = match find_workspaces(&project.root) {
+            Ok(workspaces) => workspaces,
+            Err(error) => {
+                error::report(&error);
+                std::process::exit(1);
+            }
+        };
+
+        match filter_workspaces(&workspaces, filter) {
+            Ok(selected) => selected
+                .into_iter()
+                .map(|workspace| workspace.directory)
+                .collect(),
+            Err(error) => {
+                error::report(&error);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    for directory in directories {
+        let scripts = match Scripts::from_directory(&directory) {
+            Ok(scripts) => scripts.with_reporter(Arc::new(ProgressReporter::new())),
+            Err(error) => {
+                error::report(&error);
+                std::process::exit(1);
+            }
+        };
+
+        match scripts.run(name, args) {
+            Ok(0) => (),
+            Ok(code) => std::process::exit(code),
+            Err(error) => {
+                error::report(&error);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+struct UpdateChoice {
+    name: String,
+    /// The package `name` is installed from, which differs for `npm:` aliases
+    package: String,
+    range: String,
+    latest_version: String,
+}
+
+impl std::fmt::Display for UpdateChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.name,
+            self.range.dimmed(),
+            self.latest_version.green()
+        )
+    }
+}
+
+/// Moves the ranges of the chosen packages to their latest versions in the `package.json` of
+/// the project and of every workspace that depends on them from the registry
+fn bump_ranges(project: &Project, choices: &[UpdateChoice]) -> blaze::Result<()> {
+    let mut package_json_paths = vec![project.root.join("package.json")];
+    package_json_paths.extend(
+        find_workspaces(&project.root)?
+            .iter()
+            .map(|workspace| workspace.package_json_path()),
+    );
+
+    for package_json_path in package_json_paths {
+        let mut dependencies = Dependencies::from_package_json_at(&package_json_path)?;
+        let mut changed = false;
+
+        for choice in choices {
+            for declared in [
+                &mut dependencies.dependencies,
+                &mut dependencies.dev_dependencies,
+            ] {
+                if let Some(range) = declared.get_mut(&choice.name) {
+                    let is_same_package = Specifier::parse(range)
+                        .registry_package(&choice.name)
+                        .map_or(false, |(package, _)| package == choice.package);
+
+                    if let Some(bumped) = bump_range(range, &choice.latest_version) {
+                        if is_same_package && bumped != *range {
+                            *range = bumped;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if changed {
+            dependencies.write_dependencies_to_package_json_at(&package_json_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites a range so that it starts at `version`, keeping the `^`/`~` prefix the user chose
+/// and the package an `npm:` alias points at. Wildcards and dist-tags such as `latest` already
+/// follow the newest version, so they are left untouched like every specifier that is not a
+/// registry range
+fn bump_range(spec: &str, version: &str) -> Option<String> {
+    let range = match Specifier::parse(spec) {
+        Specifier::Registry(range) => range,
+        Specifier::Alias { name, range } => {
+            return bump_range(&range, version).map(|range| format!("npm:{}@{}", name, range))
+        }
+        _ => return None,
+    };
+
+    if matches!(range.as_str(), "" | "*" | "x" | "X") || VersionRange::parse(&range).is_err() {
+        return None;
+    }
+
+    if range.starts_with('~') {
+        Some(format!("~{}", version))
+    } else if semver::Version::parse(range.trim_start_matches(['=', 'v'])).is_ok() {
+        Some(version.to_string())
+    } else {
+        Some(format!("^{}", version))
+    }
+}
+
+fn print_outdated_table(outdated: &[OutdatedDependency]) {
+    let header = ["Package", "Current", "Wanted", "Latest"];
+    let rows: Vec<[String; 4]> = outdated
+        .iter()
+        .map(|dependency| {
+            [
+                dependency.name.clone(),
+                dependency
+                    .current
+                    .clone()
+                    .unwrap_or_else(|| "MISSING".to_string()),
+                dependency.wanted.clone(),
+                dependency.latest.clone(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(|column| column.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}",
+            header[0],
+            header[1],
+            header[2],
+            header[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        )
+        .underline()
+    );
+
+    for (dependency, row) in outdated.iter().zip(&rows) {
+        // red means an update within the declared range is available, yellow means only a
+        // version outside of the range is
+        let name = format!("{:<w$}", row[0], w = widths[0]);
+        let name = if dependency.current.as_deref() != Some(&dependency.wanted) {
+            name.red()
+        } else {
+            name.yellow()
+        };
+
+        println!(
+            "{}  {:<w1$}  {}  {}",
+            name,
+            row[1],
+            format!("{:<w$}", row[2], w = widths[2]).green(),
+            format!("{:<w$}", row[3], w = widths[3]).magenta(),
+            w1 = widths[1]
+        );
+    }
+}
+
+fn print_dependency_nodes(
+    nodes: &std::collections::BTreeMap<String, DependencyNode>,
+    prefix: &str,
+) {
+    for (index, node) in nodes.values().enumerate() {
+        let is_last = index == nodes.len() - 1;
+        let branch = if is_last { "└── " } else { "├── " };
+
+        let label = if node.missing {
+            format!("UNMET DEPENDENCY {}@{}", node.name, node.range)
+                .red()
+                .to_string()
+        } else {
+            let mut label = format!(
+                "{}@{}",
+                node.name,
+                node.version.as_deref().unwrap_or("unknown")
+            );
+
+            if node.invalid {
+                label = format!(
+                    "{} {}",
+                    label.red(),
+                    format!("invalid: {}", node.range).red()
+                );
+            }
+            if node.extraneous {
+                label = format!("{} {}", label, "extraneous".red());
+            }
+            if node.deduped {
+                label = format!("{} {}", label, "deduped".dimmed());
+            }
+
+            label
+        };
+
+        println!("{}{}{}", prefix, branch, label);
+
+        let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+        print_dependency_nodes(&node.dependencies, &child_prefix);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bumps_ranges_to_the_latest_version() {
+        let cases = [
+            ("^1.2.0", Some("^2.1.0")),
+            ("~1.2.0", Some("~2.1.0")),
+            ("1.2.0", Some("2.1.0")),
+            ("=1.2.0", Some("2.1.0")),
+            ("v1.2.0", Some("2.1.0")),
+            (">=1.2.0 <2.0.0", Some("^2.1.0")),
+            ("1.x || 2.x", Some("^2.1.0")),
+            ("npm:other@^1.2.0", Some("npm:other@^2.1.0")),
+            ("npm:@scope/other@~1.2.0", Some("npm:@scope/other@~2.1.0")),
+            ("npm:other@1.2.0", Some("npm:other@2.1.0")),
+            // already following the newest version
+            ("*", None),
+            ("", None),
+            ("latest", None),
+            ("npm:other@latest", None),
+            // not on the registry
+            ("file:../lib", None),
+            ("github:user/repo", None),
+            ("https://example.com/pkg.tgz", None),
+        ];
+
+        for (range, bumped) in cases {
+            assert_eq!(
+                bump_range(range, "2.1.0").as_deref(),
+                bumped,
+                "bumping `{}`",
+                range
+            );
+        }
+    }
+}
@@ -1,8 +1,16 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 /// A Minimal And Fast NodeJS Package Manager Written In Rust
 pub struct Cli {
+    #[clap(
+        short = 'C',
+        long = "prefix",
+        global = true,
+        help = "run as if blaze was started in this directory"
+    )]
+    pub prefix: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -13,6 +21,165 @@ pub enum Command {
     Install {
         #[clap(value_delimiter = ' ')]
         package_names: Vec<String>,
+
+        #[clap(
+            short = 'w',
+            long = "filter",
+            help = "only install for the workspaces matching this name or path"
+        )]
+        filter: Vec<String>,
+
+        #[clap(
+            long = "network-concurrency",
+            help = "the maximum number of registry requests and downloads at once"
+        )]
+        network_concurrency: Option<usize>,
+
+        #[clap(
+            long = "ignore-scripts",
+            help = "do not run the lifecycle scripts of the project"
+        )]
+        ignore_scripts: bool,
+    },
+
+    #[clap(
+        name = "update",
+        about = "update packages to the newest versions allowed by their ranges"
+    )]
+    Update {
+        #[clap(value_delimiter = ' ')]
+        package_names: Vec<String>,
+
+        #[clap(long, help = "bump the ranges in package.json to the latest versions")]
+        latest: bool,
+
+        #[clap(short, long, help = "choose the packages to update interactively")]
+        interactive: bool,
+
+        #[clap(
+            long = "network-concurrency",
+            help = "the maximum number of registry requests and downloads at once"
+        )]
+        network_concurrency: Option<usize>,
+    },
+
+    #[clap(
+        name = "prune",
+        about = "remove packages from node_modules that are no longer depended on"
+    )]
+    Prune {
+        #[clap(
+            long = "dry-run",
+            help = "list what would be removed without removing it"
+        )]
+        dry_run: bool,
+    },
+
+    #[clap(
+        name = "dedupe",
+        about = "remove duplicate copies of packages by sharing versions between dependents"
+    )]
+    Dedupe,
+
+    #[clap(
+        name = "outdated",
+        about = "list dependencies that have newer versions"
+    )]
+    Outdated {
+        #[clap(long, help = "print the report as JSON")]
+        json: bool,
+    },
+
+    #[clap(name = "why", about = "explain why a package is installed")]
+    Why {
+        #[clap(help = "the package to explain, optionally as name@version")]
+        package: String,
+    },
+
+    #[clap(
+        name = "list",
+        visible_alias = "ls",
+        about = "print the tree of installed dependencies"
+    )]
+    List {
+        #[clap(long, help = "how deep into the tree to print")]
+        depth: Option<usize>,
+
+        #[clap(long, help = "print the tree as JSON")]
+        json: bool,
+
+        #[clap(long, conflicts_with = "dev", help = "only show dependencies")]
+        prod: bool,
+
+        #[clap(long, help = "only show devDependencies")]
+        dev: bool,
+    },
+
+    #[clap(name = "run", about = "run a script defined in package.json")]
+    Run {
+        #[clap(help = "the script to run, lists the available scripts when omitted")]
+        script: Option<String>,
+
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        #[clap(
+            short = 'w',
+            long = "filter",
+            help = "run the script in the workspaces matching this name or path"
+        )]
+        filter: Vec<String>,
+    },
+
+    #[clap(name = "test", about = "run the test script")]
+    Test {
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        #[clap(
+            short = 'w',
+            long = "filter",
+            help = "run the script in the workspaces matching this name or path"
+        )]
+        filter: Vec<String>,
+    },
+
+    #[clap(name = "start", about = "run the start script")]
+    Start {
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        #[clap(
+            short = 'w',
+            long = "filter",
+            help = "run the script in the workspaces matching this name or path"
+        )]
+        filter: Vec<String>,
+    },
+
+    #[clap(
+        name = "exec",
+        about = "run a binary from a package, installing the package temporarily when needed"
+    )]
+    Exec {
+        #[clap(
+            short,
+            long,
+            help = "the package providing the binary, defaults to the command"
+        )]
+        package: Option<String>,
+
+        #[clap(help = "the binary to run, optionally as name@version")]
+        command: String,
+
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    #[clap(name = "lock", about = "work with the lockfile")]
+    Lock {
+        #[command(subcommand)]
+        command: LockCommand,
     },
 
     #[clap(name = "init", about = "initialize a new NodeJS project")]
@@ -24,3 +191,26 @@ pub enum Command {
         verbose: Option<bool>,
     },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum LockCommand {
+    #[clap(
+        name = "export",
+        about = "write the lockfile in the format of another package manager"
+    )]
+    Export {
+        #[clap(
+            long,
+            value_enum,
+            default_value = "npm",
+            help = "the lockfile format to write"
+        )]
+        format: LockFormat,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LockFormat {
+    /// package-lock.json with lockfileVersion 3
+    Npm,
+}
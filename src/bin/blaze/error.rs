@@ -3,3 +3,12 @@ use colored::*;
 pub fn print_error(error_msg: &str) {
     println!("{}: {}", "Error".red(), error_msg);
 }
+
+/// Prints an error of the blaze library along with a hint on how to fix it, if there is one
+pub fn report(error: &blaze::Error) {
+    print_error(&error.to_string());
+
+    if let Some(hint) = error.hint() {
+        println!("{}: {}", "Hint".yellow(), hint);
+    }
+}
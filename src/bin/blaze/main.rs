@@ -1,12 +1,28 @@
+use blaze::project::Project;
 use clap::Parser;
 
 pub mod actions;
 pub mod cli;
 pub mod error;
+pub mod progress;
 pub mod version;
 
 #[tokio::main]
 async fn main() {
     let cli = cli::Cli::parse();
-    cli.command.run().await;
+
+    let start = match &cli.prefix {
+        Some(prefix) => prefix.clone(),
+        None => std::path::PathBuf::from("."),
+    };
+
+    let project = match Project::discover(&start) {
+        Ok(project) => project,
+        Err(error) => {
+            error::report(&error);
+            std::process::exit(1);
+        }
+    };
+
+    cli.command.run(&project).await;
 }
@@ -0,0 +1,255 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use blaze::reporter::{Event, Reporter};
+use colored::Colorize;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Shows each phase of an install as its own progress bar, printing messages above them, and
+/// a summary once the install is done. When stdout is not a terminal, every phase prints a
+/// single line when it ends instead
+pub struct ProgressReporter {
+    bars: MultiProgress,
+    /// Whether stdout is a terminal the bars can be drawn on
+    interactive: bool,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+/// The bars of the phases that are running and what the phases did so far
+#[derive(Default)]
+struct State {
+    resolving: Option<ProgressBar>,
+    fetching: Option<ProgressBar>,
+    linking: Option<ProgressBar>,
+    scripting: Option<ProgressBar>,
+    fetched: usize,
+    cached: usize,
+    bytes: u64,
+    linked: usize,
+    removed: usize,
+    scripts: usize,
+}
+
+impl ProgressReporter {
+    pub fn new() -> ProgressReporter {
+        let bars = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+
+        ProgressReporter {
+            // the draw target is hidden when stdout is not a terminal
+            interactive: !bars.is_hidden(),
+            bars,
+            started: Instant::now(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Prints how many packages were added or removed and how long the install took, such as
+    /// "added 312 packages in 4.2s, 280 from cache"
+    pub fn finish(&self) {
+        let state = self.state.lock().unwrap();
+        let elapsed = self.started.elapsed().as_secs_f64();
+
+        let mut changes = Vec::new();
+        if state.linked > 0 {
+            changes.push(format!("added {}", packages(state.linked)));
+        }
+        if state.removed > 0 {
+            changes.push(format!("removed {}", packages(state.removed)));
+        }
+        if changes.is_empty() {
+            changes.push("up to date".to_string());
+        }
+
+        let mut summary = format!("{} in {:.1}s", changes.join(", "), elapsed);
+        if state.fetched > 0 {
+            summary.push_str(&format!(", {} from cache", state.cached));
+        }
+
+        self.println(&summary);
+    }
+
+    /// Adds the bar of a phase below the bars of the phases before it, or nothing when the
+    /// bars are not drawn
+    fn start_phase(&self, bar: ProgressBar, template: &str) -> Option<ProgressBar> {
+        if !self.interactive {
+            return None;
+        }
+
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("#>-"),
+        );
+        bar.enable_steady_tick(Duration::from_millis(100));
+
+        Some(self.bars.add(bar))
+    }
+
+    /// Replaces the bar of a phase that ended with `message`, or prints `message` when the
+    /// bars are not drawn
+    fn finish_phase(&self, bar: Option<ProgressBar>, message: String) {
+        match bar {
+            Some(bar) => {
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{msg}")
+                        .unwrap_or_else(|_| ProgressStyle::default_bar()),
+                );
+                bar.finish_with_message(message);
+            }
+            None => println!("{}", message),
+        }
+    }
+
+    fn println(&self, message: &str) {
+        if !self.interactive || self.bars.println(message).is_err() {
+            println!("{}", message);
+        }
+    }
+}
+
+impl Default for ProgressReporter {
+    fn default() -> ProgressReporter {
+        ProgressReporter::new()
+    }
+}
+
+impl Reporter for ProgressReporter {
+    fn report(&self, event: Event) {
+        let mut state = self.state.lock().unwrap();
+
+        match event {
+            Event::ResolveStarted => {
+                state.resolving = self.start_phase(
+                    ProgressBar::new_spinner(),
+                    "{spinner:.green} Resolving {pos} packages {wide_msg:.dim}",
+                );
+            }
+            Event::Resolved { name, version } => {
+                if let Some(bar) = &state.resolving {
+                    bar.inc(1);
+                    bar.set_message(format!("{}@{}", name, version));
+                }
+            }
+            Event::ResolveFinished { packages: count } => {
+                self.finish_phase(
+                    state.resolving.take(),
+                    format!("Resolved {}", packages(count)),
+                );
+            }
+            Event::FetchStarted { packages } => {
+                state.fetching = self.start_phase(
+                    ProgressBar::new(packages as u64),
+                    "{spinner:.green} Fetching  [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}",
+                );
+            }
+            Event::Fetched { bytes, cached, .. } => {
+                state.fetched += 1;
+                state.bytes += bytes;
+                if cached {
+                    state.cached += 1;
+                }
+
+                if let Some(bar) = &state.fetching {
+                    let seconds = bar.elapsed().as_secs_f64().max(0.001);
+                    bar.inc(1);
+                    bar.set_message(format!(
+                        "{} at {}/s",
+                        HumanBytes(state.bytes),
+                        HumanBytes((state.bytes as f64 / seconds) as u64)
+                    ));
+                }
+            }
+            Event::FetchFinished => {
+                let message = format!(
+                    "Fetched {}, {} downloaded, {} from cache",
+                    packages(state.fetched),
+                    HumanBytes(state.bytes),
+                    state.cached
+                );
+                self.finish_phase(state.fetching.take(), message);
+            }
+            Event::LinkStarted { packages } => {
+                state.linking = self.start_phase(
+                    ProgressBar::new(packages as u64),
+                    "{spinner:.green} Linking   [{bar:40.cyan/blue}] {pos}/{len} {wide_msg:.dim}",
+                );
+            }
+            Event::Linked { name } => {
+                state.linked += 1;
+
+                if let Some(bar) = &state.linking {
+                    bar.inc(1);
+                    bar.set_message(name);
+                }
+            }
+            Event::LinkFinished => {
+                let message = format!("Linked {}", packages(state.linked));
+                self.finish_phase(state.linking.take(), message);
+            }
+            Event::Removed { .. } => state.removed += 1,
+            Event::ScriptsStarted { scripts } => {
+                state.scripting = self.start_phase(
+                    ProgressBar::new(scripts as u64),
+                    "{spinner:.green} Running   [{bar:40.cyan/blue}] {pos}/{len} {wide_msg:.dim}",
+                );
+
+                // scripts print to the terminal themselves, so the bar is only redrawn in
+                // between them rather than over their output
+                if let Some(bar) = &state.scripting {
+                    bar.disable_steady_tick();
+                }
+            }
+            Event::ScriptStarted {
+                package,
+                script,
+                command,
+            } => match &state.scripting {
+                Some(bar) => bar.set_message(format!("{} {}", package, script)),
+                None => self.println(&format!("\n> {} {}\n> {}\n", package, script, command)),
+            },
+            Event::ScriptFinished {
+                package,
+                script,
+                code,
+            } => {
+                state.scripts += 1;
+
+                if let Some(bar) = &state.scripting {
+                    bar.inc(1);
+                }
+                // a failing script ends the install, so its phase never finishes
+                if code != 0 && state.scripting.is_some() {
+                    let message = format!("{} {} exited with code {}", package, script, code);
+                    self.finish_phase(state.scripting.take(), message);
+                }
+            }
+            Event::ScriptsFinished => {
+                let message = format!("Ran {} lifecycle {}", state.scripts, scripts(state.scripts));
+                self.finish_phase(state.scripting.take(), message);
+            }
+            Event::Info { message } => self.println(&message),
+            Event::Warning { message } => {
+                self.println(&format!("{}: {}", "Warning".yellow(), message))
+            }
+        }
+    }
+}
+
+fn scripts(count: usize) -> &'static str {
+    if count == 1 {
+        "script"
+    } else {
+        "scripts"
+    }
+}
+
+fn packages(count: usize) -> String {
+    if count == 1 {
+        "1 package".to_string()
+    } else {
+        format!("{} packages", count)
+    }
+}
@@ -0,0 +1,64 @@
+use blaze::concurrency::{Concurrency, DEFAULT_NETWORK_CONCURRENCY};
+use blaze::dependencies::Dependencies;
+use blaze::npmrc::Npmrc;
+use blaze::reporter::Event;
+use clap::Parser;
+use colored::Colorize;
+
+#[derive(Parser)]
+/// Run a binary from a NodeJS package, installing the package temporarily when needed
+struct Cli {
+    #[clap(
+        short,
+        long,
+        help = "the package providing the binary, defaults to the command"
+    )]
+    package: Option<String>,
+
+    #[clap(help = "the binary to run, optionally as name@version")]
+    command: String,
+
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let current_dir = match std::env::current_dir() {
+        Ok(current_dir) => current_dir,
+        Err(error) => {
+            println!("{}: {}", "Error".red(), error);
+            std::process::exit(1);
+        }
+    };
+
+    // like `blaze exec`, downloads honour `maxsockets` in `.npmrc`
+    let network = Npmrc::load(&current_dir)
+        .maxsockets()
+        .unwrap_or(DEFAULT_NETWORK_CONCURRENCY);
+
+    match blaze::exec::exec(
+        &current_dir,
+        Dependencies::default().with_concurrency(Concurrency::with_network_limit(network)),
+        std::sync::Arc::new(|event| match event {
+            Event::Info { message } | Event::Warning { message } => println!("{}", message),
+            _ => (),
+        }),
+        &cli.command,
+        cli.package.as_deref(),
+        &cli.args,
+    )
+    .await
+    {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            println!("{}: {}", "Error".red(), error);
+            if let Some(hint) = error.hint() {
+                println!("{}: {}", "Hint".yellow(), hint);
+            }
+            std::process::exit(1);
+        }
+    }
+}
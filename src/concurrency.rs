@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::specifier::Specifier;
+
+/// The number of registry requests and downloads run at once unless configured otherwise
+pub const DEFAULT_NETWORK_CONCURRENCY: usize = 16;
+
+/// Limits how much of an install runs at once. Every phase shares the same limits, so the
+/// resolver and the fetcher together never open more than `network` connections
+#[derive(Debug, Clone)]
+pub struct Concurrency {
+    network: Arc<Semaphore>,
+    io: Arc<Semaphore>,
+}
+
+impl Default for Concurrency {
+    fn default() -> Concurrency {
+        Concurrency::with_network_limit(DEFAULT_NETWORK_CONCURRENCY)
+    }
+}
+
+impl Concurrency {
+    /// Limits are raised to at least one
+    pub fn new(network: usize, io: usize) -> Concurrency {
+        Concurrency {
+            network: Arc::new(Semaphore::new(network.max(1))),
+            io: Arc::new(Semaphore::new(io.max(1))),
+        }
+    }
+
+    /// Allows `network` requests at once, and as many file system operations as there are CPUs
+    pub fn with_network_limit(network: usize) -> Concurrency {
+        let io = std::thread::available_parallelism().map_or(4, |cpus| cpus.get());
+
+        Concurrency::new(network, io)
+    }
+
+    /// Waits for a free network slot, held until the permit is dropped
+    pub async fn network(&self) -> OwnedSemaphorePermit {
+        self.network
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the network semaphore is never closed")
+    }
+
+    /// Waits for a free slot to extract, copy or link a package, held until the permit is dropped
+    pub async fn io(&self) -> OwnedSemaphorePermit {
+        self.io
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the io semaphore is never closed")
+    }
+
+    /// The slot to hold while fetching a package from `sources`: a network slot for remote
+    /// tarballs and git repositories, and a file system slot for local directories and tarballs
+    pub async fn source(&self, specifier: &Specifier) -> OwnedSemaphorePermit {
+        match specifier {
+            Specifier::RemoteTarball(_) | Specifier::Git { .. } => self.network().await,
+            _ => self.io().await,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use semver::Version;
+use std::collections::BTreeMap;
+
+use crate::concurrency::Concurrency;
+use crate::error::Result;
+use crate::lockfile::{find_installed, installed_name, LockedPackage};
+use crate::registry::Registry;
+use crate::specifier::Specifier;
+use crate::version_range::{satisfies, VersionRange};
+
+/// What `Dependencies::dedupe` changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupeReport {
+    /// How many copies of packages installed more than once went away
+    pub duplicates_removed: usize,
+    /// How many bytes `node_modules` shrank by, zero when it did not shrink
+    pub bytes_saved: u64,
+    /// The packages kept at the same install path that moved to another version, as
+    /// `(path, old version, new version)`
+    pub changed: Vec<(String, String, String)>,
+}
+
+/// A range a package is requested with
+struct Request {
+    /// Whether the project itself depends on the package, in which case its range is binding
+    from_project: bool,
+    range: String,
+}
+
+/// Counts the copies of packages beyond the first one, for every name installed at more than
+/// one path
+pub fn count_duplicates(packages: &BTreeMap<String, LockedPackage>) -> usize {
+    let mut copies: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for path in packages.keys() {
+        *copies.entry(installed_name(path)).or_default() += 1;
+    }
+
+    copies.values().map(|count| count - 1).sum()
+}
+
+/// Picks, for every package installed at more than one path, the published version satisfying
+/// the ranges of the most dependents, preferring newer versions on a tie. The range of the
+/// project always has to be satisfied. Returns the versions to prefer when resolving again:
+/// the locked versions of `packages` by install path, except that every copy of a duplicated
+/// package gives way to its pick. Packuments are fetched within the network limit of
+/// `concurrency`
+pub async fn prefer_shared_versions(
+    registry: &dyn Registry,
+    concurrency: &Concurrency,
+    root_dependencies: &BTreeMap<String, String>,
+    packages: &BTreeMap<String, LockedPackage>,
+) -> Result<BTreeMap<String, String>> {
+    let mut preferred_versions: BTreeMap<String, String> = packages
+        .iter()
+        .map(|(path, package)| (path.clone(), package.version.clone()))
+        .collect();
+
+    for (name, requests) in requests(root_dependencies, packages) {
+        let copies: Vec<&LockedPackage> = packages
+            .iter()
+            .filter(|(path, _)| installed_name(path) == name)
+            .map(|(_, package)| package)
+            .collect();
+        if copies.len() < 2 {
+            continue;
+        }
+
+        let permit = concurrency.network().await;
+        let packument = registry.fetch_packument(&copies[0].name).await?;
+        drop(permit);
+        let mut versions: Vec<Version> = match packument["versions"].as_object() {
+            Some(versions) => versions
+                .keys()
+                .filter_map(|version| Version::parse(version).ok())
+                .collect(),
+            None => continue,
+        };
+        versions.sort_by(|a, b| b.cmp(a));
+
+        let mut best: Option<(String, usize)> = None;
+
+        for version in versions {
+            let version = version.to_string();
+
+            let allowed = requests
+                .iter()
+                .filter(|request| request.from_project)
+                .all(|request| satisfies(&version, &request.range));
+
+            let count = requests
+                .iter()
+                .filter(|request| satisfies(&version, &request.range))
+                .count();
+
+            if allowed && best.as_ref().map_or(true, |(_, best)| count > *best) {
+                best = Some((version, count));
+            }
+        }
+
+        if let Some((version, _)) = best {
+            preferred_versions.retain(|path, _| installed_name(path) != name);
+            preferred_versions.insert(name, version);
+        }
+    }
+
+    Ok(preferred_versions)
+}
+
+/// The registry ranges every installed package is requested with, by the project and by the
+/// packages depending on it, keyed by the name it is installed under. Dist-tags and other
+/// specifiers that are not version ranges are left out, since no version can be checked
+/// against them
+fn requests(
+    root_dependencies: &BTreeMap<String, String>,
+    packages: &BTreeMap<String, LockedPackage>,
+) -> BTreeMap<String, Vec<Request>> {
+    let mut requests: BTreeMap<String, Vec<Request>> = BTreeMap::new();
+
+    let dependencies = root_dependencies
+        .iter()
+        .map(|dependency| ("", dependency))
+        .chain(packages.iter().flat_map(|(path, package)| {
+            package
+                .dependencies
+                .iter()
+                .map(move |dependency| (path.as_str(), dependency))
+        }));
+
+    for (dependent, (name, spec)) in dependencies {
+        let package = match find_installed(packages, dependent, name) {
+            Some(path) => &packages[&path],
+            None => continue,
+        };
+
+        let (real_name, range) = match Specifier::parse(spec).registry_package(name) {
+            Some(registry_package) => registry_package,
+            None => continue,
+        };
+
+        if real_name != package.name || VersionRange::parse(&range).is_err() {
+            continue;
+        }
+
+        requests.entry(name.clone()).or_default().push(Request {
+            from_project: dependent.is_empty(),
+            range,
+        });
+    }
+
+    requests
+}
@@ -1,405 +1,853 @@
-use async_recursion::async_recursion;
-use indicatif::{ProgressBar, ProgressStyle};
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::error::Error;
-use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tokio::time;
-
-use crate::utils::{copy_folder_contents, read_package_json};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::concurrency::Concurrency;
+use crate::dedupe::{count_duplicates, prefer_shared_versions, DedupeReport};
+use crate::error::{Error, Result};
+use crate::fetcher::Fetcher;
+use crate::import::ImportedLockfile;
+use crate::install_state::InstallState;
+use crate::linker::Linker;
+use crate::lockfile::{installed_name, LockedPackage, Lockfile, LOCKFILE_NAME};
+use crate::overrides::Overrides;
+use crate::prune::{find_extraneous, prune, Extraneous};
+use crate::registry::{default_registry, Registry};
+use crate::reporter::{Event, Reporter, SilentReporter};
+use crate::resolver::{ResolvedGraph, Resolver};
+use crate::scripts::Scripts;
+use crate::specifier::Specifier;
+use crate::store::Store;
+use crate::utils::{default_cache_directory, directory_size, JsonStyle};
+use crate::version_range::{satisfies, VersionRange};
+use crate::workspaces::Workspace;
+
+/// The lifecycle scripts of the project that run before its dependencies are installed
+pub const PREINSTALL_SCRIPTS: [&str; 1] = ["preinstall"];
+
+/// The lifecycle scripts of the project that run after its dependencies are installed, in the
+/// order npm runs them
+pub const INSTALL_SCRIPTS: [&str; 3] = ["install", "postinstall", "prepare"];
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct Dependencies {
-    pub dependencies: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(rename = "devDependencies", default)]
+    pub dev_dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    pub overrides: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub resolutions: BTreeMap<String, String>,
+    /// Workspace packages that are linked into `node_modules` instead of being downloaded,
+    /// mapped to their directories
+    #[serde(skip)]
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// The directory holding `package.json`, which `node_modules` and `blaze.lock` live next to
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// Where registry packages are resolved and downloaded from, npm's public registry when
+    /// unset
+    #[serde(skip)]
+    pub registry: Option<Arc<dyn Registry>>,
+    /// How many requests and file operations an install runs at once
+    #[serde(skip)]
+    pub concurrency: Concurrency,
+    /// The dependencies a filtered install is limited to, set by `select_workspaces`. The
+    /// packages only other workspaces need are still resolved and locked, and are left alone in
+    /// `node_modules`, but are not installed. Everything is installed when unset
+    #[serde(skip)]
+    pub selected: Option<BTreeSet<String>>,
+    /// The blaze cache holding the store and the fetched sources, the default cache directory
+    /// of the platform when unset
+    #[serde(skip)]
+    pub cache_directory: Option<PathBuf>,
 }
 
 impl Dependencies {
-    pub fn from_package_json() -> Result<Dependencies, Box<dyn std::error::Error>> {
-        let contents = read_package_json()?;
-        let package_json: Dependencies = serde_json::from_str(&contents)?;
-        Ok(package_json)
+    /// Reads the `package.json` of the project in `root`
+    pub fn from_package_json(root: &Path) -> Result<Dependencies> {
+        Dependencies::from_package_json_at(&root.join("package.json"))
     }
 
-    pub fn write_dependencies_to_package_json(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if !Path::new("package.json").exists() {
-            let mut file = File::create("package.json")?;
-            let buffer = serde_json::to_value(&self.dependencies)?;
-            file.write_all(serde_json::to_string_pretty(&buffer)?.as_bytes())?;
+    pub fn from_package_json_at(path: &Path) -> Result<Dependencies> {
+        let contents = fs::read_to_string(path).map_err(Error::io(path))?;
+
+        match serde_json::from_str::<Dependencies>(&contents) {
+            Ok(mut dependencies) => {
+                dependencies.root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                Ok(dependencies)
+            }
+            Err(error) => Err(Error::manifest(path.display(), error)),
         }
+    }
 
-        let mut file = File::open("package.json")?;
+    pub fn with_registry(mut self, registry: Arc<dyn Registry>) -> Dependencies {
+        self.registry = Some(registry);
+        self
+    }
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
+    pub fn with_concurrency(mut self, concurrency: Concurrency) -> Dependencies {
+        self.concurrency = concurrency;
+        self
+    }
 
-        let mut package_json: Value = serde_json::from_str(&contents)?;
+    pub fn with_cache_directory(mut self, cache_directory: &Path) -> Dependencies {
+        self.cache_directory = Some(cache_directory.to_path_buf());
+        self
+    }
 
-        package_json["dependencies"] = serde_json::to_value(&self.dependencies)?;
+    pub fn registry(&self) -> Arc<dyn Registry> {
+        self.registry.clone().unwrap_or_else(default_registry)
+    }
 
-        let mut file = File::create("package.json")?;
-        file.write_all(serde_json::to_string_pretty(&package_json)?.as_bytes())?;
+    pub fn cache_directory(&self) -> Result<PathBuf> {
+        match &self.cache_directory {
+            Some(cache_directory) => Ok(cache_directory.clone()),
+            None => default_cache_directory(),
+        }
+    }
 
-        Ok(())
+    pub fn write_dependencies_to_package_json(&self) -> Result<()> {
+        self.write_dependencies_to_package_json_at(&self.root.join("package.json"))
     }
 
-    pub async fn download_dependencies(&self) -> Result<(), String> {
-        let mut all_dependencies = std::collections::BTreeMap::new();
+    /// Writes the dependencies into `package.json`, keeping the order of its keys, its
+    /// indentation and its line endings
+    pub fn write_dependencies_to_package_json_at(&self, path: &Path) -> Result<()> {
+        let (mut package_json, style) = if path.exists() {
+            let contents = fs::read_to_string(path).map_err(Error::io(path))?;
 
-        for dependency in self.dependencies.clone() {
-            let name = dependency.0;
-            let version_req = dependency.1.replace('~', "");
-            let version_req = version_req.replace('^', "");
+            match serde_json::from_str(&contents) {
+                Ok(package_json) => (package_json, JsonStyle::detect(&contents)),
+                Err(error) => return Err(Error::manifest(path.display(), error)),
+            }
+        } else {
+            (Value::Object(Map::new()), JsonStyle::default())
+        };
 
-            let version = match get_version(&name, &version_req).await {
-                Ok(version) => version,
-                Err(error) => return Err(error),
-            };
+        // like npm, the dependencies are sorted by name
+        package_json["dependencies"] = json!(self.dependencies);
 
-            let related_dependencies = match get_related_dependencies(
-                name.clone(),
-                version.clone(),
-                self.dependencies.clone(),
-            )
-            .await
-            {
-                Ok(related_dependencies) => related_dependencies,
-                Err(error) => {
-                    println!(
-                        "Could not get related dependencies for {}@{}: {}",
-                        name, version, error
-                    );
-                    continue;
-                }
+        if !self.dev_dependencies.is_empty() {
+            package_json["devDependencies"] = json!(self.dev_dependencies);
+        }
+
+        let contents = match style.to_string(&package_json) {
+            Ok(contents) => contents,
+            Err(error) => return Err(Error::manifest(path.display(), error)),
+        };
+
+        fs::write(path, contents).map_err(Error::io(path))
+    }
+
+    /// Merges the dependencies of the `selected` workspaces into these ones. Every workspace in
+    /// `all` gets linked into `node_modules`, and dependencies on a workspace whose version
+    /// satisfies the requested range are served by that link instead of the registry. The
+    /// `file:` and `link:` paths of a workspace are relative to its own directory, so they are
+    /// rewritten to be relative to the project root.
+    ///
+    /// Workspaces are symlinked, so their dependencies have no `node_modules` to be nested in
+    /// and a single version of each is installed. The range of the project wins over the
+    /// ranges of its workspaces, and the first workspace declaring a dependency wins over the
+    /// later ones. Ranges that no version satisfies together fail with an `Error::Workspace`
+    /// naming both sides, except for dependencies on the workspaces themselves
+    pub fn add_workspaces(&mut self, selected: &[Workspace], all: &[Workspace]) -> Result<()> {
+        let mut declared_by: BTreeMap<String, String> = self
+            .root_dependencies()
+            .into_keys()
+            .map(|name| (name, "the project".to_string()))
+            .collect();
+        let workspace_names: BTreeSet<&str> = all
+            .iter()
+            .map(|workspace| workspace.name.as_str())
+            .collect();
+
+        for workspace in selected {
+            self.merge_workspace_dependencies(
+                workspace,
+                workspace.dependencies()?,
+                &workspace_names,
+                &mut declared_by,
+            )?;
+        }
+
+        for workspace in all {
+            let is_satisfied_locally = |range: &String| {
+                range.starts_with("workspace:") || satisfies(&workspace.version, range)
             };
 
-            for (name, version) in related_dependencies {
-                all_dependencies.insert(name, version);
+            if self
+                .dependencies
+                .get(&workspace.name)
+                .map_or(false, is_satisfied_locally)
+            {
+                self.dependencies.remove(&workspace.name);
+            }
+            if self
+                .dev_dependencies
+                .get(&workspace.name)
+                .map_or(false, is_satisfied_locally)
+            {
+                self.dev_dependencies.remove(&workspace.name);
             }
 
-            all_dependencies.insert(name.to_string(), version);
+            self.workspaces
+                .insert(workspace.name.clone(), workspace.path.clone());
         }
 
-        if !Path::new("node_modules").exists() {
-            if let Err(e) = fs::create_dir("node_modules") {
-                return Err(e.to_string());
+        Ok(())
+    }
+
+    /// Limits installs to the dependencies of the project and of the `selected` workspaces,
+    /// along with those of the workspaces out of `all` they depend on. `add_workspaces` still
+    /// has to add every workspace, so that the lockfile covers all of them
+    pub fn select_workspaces(&mut self, selected: &[Workspace], all: &[Workspace]) -> Result<()> {
+        let mut names: BTreeSet<String> = Dependencies::from_package_json(&self.root)?
+            .root_dependencies()
+            .into_keys()
+            .collect();
+
+        let mut queue: Vec<&Workspace> = selected
+            .iter()
+            .chain(
+                all.iter()
+                    .filter(|workspace| names.contains(&workspace.name)),
+            )
+            .collect();
+        let mut visited = BTreeSet::new();
+
+        while let Some(workspace) = queue.pop() {
+            if !visited.insert(workspace.name.clone()) {
+                continue;
             }
-        }
 
-        let mut download_tasks = Vec::new();
-        let amount_of_dependencies = all_dependencies.len();
+            for name in workspace.dependencies()?.root_dependencies().into_keys() {
+                queue.extend(all.iter().filter(|workspace| workspace.name == name));
+                names.insert(name);
+            }
+        }
 
-        let count = Arc::new(Mutex::new(0));
+        self.selected = Some(names);
+        Ok(())
+    }
 
-        let progress_count = count.clone();
-        let progress_bar_thread = tokio::task::spawn(async move {
-            let pb = ProgressBar::new(amount_of_dependencies as u64);
-            pb.set_style(
-                ProgressStyle::default_bar()
-                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-                    .unwrap()
-                    .progress_chars("#>-"),
+    /// Adds the dependencies a workspace declares, keeping the ranges already declared.
+    /// `declared_by` maps every dependency to the workspace (or project) whose range was kept
+    fn merge_workspace_dependencies(
+        &mut self,
+        workspace: &Workspace,
+        dependencies: Dependencies,
+        workspace_names: &BTreeSet<&str>,
+        declared_by: &mut BTreeMap<String, String>,
+    ) -> Result<()> {
+        let declared = dependencies
+            .dependencies
+            .into_iter()
+            .map(|dependency| (dependency, false))
+            .chain(
+                dependencies
+                    .dev_dependencies
+                    .into_iter()
+                    .map(|dependency| (dependency, true)),
             );
 
-            loop {
-                let count = *progress_count.lock().unwrap();
-
-                pb.set_position(count as u64);
-                if count >= amount_of_dependencies {
-                    pb.finish();
-                    break;
+        for ((name, spec), is_dev) in declared {
+            let spec = rebase_local_spec(&spec, &workspace.path);
+
+            let kept = self
+                .dependencies
+                .get(&name)
+                .or_else(|| self.dev_dependencies.get(&name));
+
+            match kept {
+                Some(kept) => {
+                    if !workspace_names.contains(name.as_str())
+                        && !specs_intersect(&name, kept, &spec)
+                    {
+                        return Err(Error::Workspace {
+                            reason: format!(
+                                "{} and {} depend on incompatible versions of {}: {} and {}",
+                                declared_by[&name], workspace.name, name, kept, spec
+                            ),
+                        });
+                    }
                 }
+                None => {
+                    declared_by.insert(name.clone(), workspace.name.clone());
 
-                time::sleep(Duration::from_millis(100)).await;
+                    if is_dev {
+                        self.dev_dependencies.insert(name, spec);
+                    } else {
+                        self.dependencies.insert(name, spec);
+                    }
+                }
             }
-        });
+        }
 
-        for (name, mut version) in all_dependencies.clone() {
-            let count = count.clone();
-            version = version.replace('~', "");
-            version = version.replace('^', "");
+        Ok(())
+    }
 
-            if version.contains('*') {
-                version = match get_latest_version(&name).await {
-                    Ok(version) => version,
-                    Err(error) => return Err(error),
+    /// Every dependency the project declares, including its `devDependencies`
+    pub fn root_dependencies(&self) -> BTreeMap<String, String> {
+        let mut root_dependencies = self.dev_dependencies.clone();
+        root_dependencies.extend(self.dependencies.clone());
+        root_dependencies
+    }
+
+    /// Installs the dependencies locked in `blaze.lock`, resolving them again (and writing the
+    /// lockfile) when `package.json` changed since it was written. The lockfile is only written
+    /// once the install succeeded, so a failed install leaves the previous one locked
+    pub async fn download_dependencies(&self, reporter: Arc<dyn Reporter>) -> Result<()> {
+        let (graph, resolved) = match Lockfile::read(&self.root) {
+            Some(lockfile)
+                if lockfile.dependencies == self.dependencies
+                    && lockfile.dev_dependencies == self.dev_dependencies
+                    && lockfile.workspaces == self.workspace_paths()
+                    && lockfile.overrides == self.overrides()?.to_lock() =>
+            {
+                let graph = ResolvedGraph {
+                    packages: lockfile.packages,
+                    workspaces: BTreeMap::new(),
+                    sources: BTreeMap::new(),
                 };
+                (graph, false)
             }
+            Some(_) => {
+                let graph = self
+                    .resolver(BTreeMap::new(), reporter.clone())?
+                    .resolve(&self.root_dependencies())
+                    .await?;
+                (graph, true)
+            }
+            None => (
+                self.resolve_imported_dependencies(reporter.clone()).await?,
+                true,
+            ),
+        };
 
-            let download_task = tokio::task::spawn(async move {
-                if let Err(e) = download_dependency(name, version).await {
-                    println!("Could not download dependency: {}", e);
-                }
+        self.install(&graph, &self.root.join("node_modules"), reporter)
+            .await?;
 
-                let mut count = count.lock().unwrap();
-                *count += 1;
-            });
-            download_tasks.push(download_task);
+        if resolved {
+            self.write_lockfile(&graph.packages)?;
         }
 
-        for download_task in download_tasks {
-            download_task.await.unwrap();
+        Ok(())
+    }
+
+    /// Runs the scripts out of `names` that the project defines, such as `PREINSTALL_SCRIPTS`
+    /// or `INSTALL_SCRIPTS`, stopping at the first one that fails. The scripts of dependencies
+    /// are never run
+    pub fn run_install_scripts(&self, names: &[&str], reporter: Arc<dyn Reporter>) -> Result<()> {
+        let scripts = Scripts::from_directory(&self.root)?.with_reporter(reporter.clone());
+        let install_scripts: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| scripts.scripts.contains_key(*name))
+            .collect();
+
+        if install_scripts.is_empty() {
+            return Ok(());
         }
 
-        progress_bar_thread.await.unwrap();
+        reporter.report(Event::ScriptsStarted {
+            scripts: install_scripts.len(),
+        });
 
-        let mut extraction_tasks = Vec::new();
+        for name in install_scripts {
+            let code = scripts.run_lifecycle(name)?.unwrap_or(0);
 
-        for (name, _version) in all_dependencies {
-            let extraction_task = tokio::task::spawn(extract_dependency(name.clone()));
-            extraction_tasks.push(extraction_task);
+            if code != 0 {
+                return Err(Error::ScriptFailed {
+                    name: name.to_string(),
+                    code,
+                });
+            }
         }
 
-        for extraction_task in extraction_tasks {
-            match extraction_task.await {
-                Ok(_) => (),
-                Err(error) => return Err(error.to_string()),
-            };
-        }
+        reporter.report(Event::ScriptsFinished);
 
         Ok(())
     }
-}
 
-pub async fn get_latest_version(package_name: &str) -> Result<String, String> {
-    let url = format!("https://registry.npmjs.org/{}", package_name);
+    /// Resolves the dependencies of a project without `blaze.lock`, keeping the versions and
+    /// integrity hashes locked by the `package-lock.json`, `yarn.lock` or `pnpm-lock.yaml` of
+    /// another package manager if there is one
+    async fn resolve_imported_dependencies(
+        &self,
+        reporter: Arc<dyn Reporter>,
+    ) -> Result<ResolvedGraph> {
+        let imported = match ImportedLockfile::read(&self.root, &self.root_dependencies()) {
+            Ok(Some(imported)) => imported,
+            Ok(None) => {
+                return self
+                    .resolver(BTreeMap::new(), reporter)?
+                    .resolve(&self.root_dependencies())
+                    .await
+            }
+            Err(error) => {
+                reporter.report(Event::Warning {
+                    message: error.to_string(),
+                });
+                return self
+                    .resolver(BTreeMap::new(), reporter)?
+                    .resolve(&self.root_dependencies())
+                    .await;
+            }
+        };
 
-    let response = match reqwest::get(&url).await {
-        Ok(response) => response,
-        Err(error) => return Err(error.to_string()),
-    };
+        reporter.report(Event::Info {
+            message: format!("Importing {}", imported.file_name),
+        });
 
-    let text = match response.text().await {
-        Ok(text) => text,
-        Err(error) => return Err(error.to_string()),
-    };
+        let mut graph = self
+            .resolver(imported.preferred_versions(reporter.as_ref()), reporter)?
+            .resolve(&self.root_dependencies())
+            .await?;
+        imported.apply(&mut graph.packages);
 
-    let json: serde_json::Value = match serde_json::from_str(&text) {
-        Ok(json) => json,
-        Err(error) => return Err(error.to_string()),
-    };
+        Ok(graph)
+    }
 
-    Ok(String::from(json["dist-tags"]["latest"].as_str().unwrap()))
-}
+    /// Resolves and installs the dependencies into `node_modules` without consulting or
+    /// writing `blaze.lock`
+    pub async fn install_to(&self, node_modules: &Path, reporter: Arc<dyn Reporter>) -> Result<()> {
+        let graph = self
+            .resolver(BTreeMap::new(), reporter.clone())?
+            .resolve(&self.root_dependencies())
+            .await?;
 
-pub async fn get_version(package_name: &str, version_req: &str) -> Result<String, String> {
-    let url = format!("https://registry.npmjs.org/{}", package_name);
+        self.install(&graph, node_modules, reporter).await
+    }
 
-    let response = match reqwest::get(&url).await {
-        Ok(response) => response,
-        Err(error) => return Err(error.to_string()),
-    };
+    /// Re-resolves the given packages (or every package when `package_names` is empty) to the
+    /// newest versions satisfying their ranges while keeping the rest of the lockfile intact.
+    /// The lockfile is only written once the install succeeded
+    pub async fn update_dependencies(
+        &self,
+        package_names: &[String],
+        reporter: Arc<dyn Reporter>,
+    ) -> Result<()> {
+        let mut preferred_versions = match Lockfile::read(&self.root) {
+            Some(lockfile) => lockfile.versions(),
+            None => BTreeMap::new(),
+        };
 
-    let text = match response.text().await {
-        Ok(text) => text,
-        Err(error) => return Err(error.to_string()),
-    };
+        if package_names.is_empty() {
+            preferred_versions.clear();
+        } else {
+            preferred_versions.retain(|path, _| {
+                !package_names
+                    .iter()
+                    .any(|name| name == installed_name(path))
+            });
+        }
 
-    let json: serde_json::Value = match serde_json::from_str(&text) {
-        Ok(json) => json,
-        Err(error) => return Err(error.to_string()),
-    };
+        let graph = self
+            .resolver(preferred_versions, reporter.clone())?
+            .resolve(&self.root_dependencies())
+            .await?;
 
-    let versions = match json["versions"].as_object() {
-        Some(versions) => versions,
-        None => return Err("Could not find versions".to_string()),
-    };
+        self.install(&graph, &self.root.join("node_modules"), reporter)
+            .await?;
 
-    let version_req_semver = match Version::parse(version_req) {
-        Ok(version_req_semver) => version_req_semver,
-        Err(error) => return Err(error.to_string()),
-    };
+        self.write_lockfile(&graph.packages)
+    }
 
-    let mut latest_version: Option<Version> = None;
+    /// Removes the packages and binaries in `node_modules` that `blaze.lock` and the workspaces
+    /// do not account for, returning what was removed. Nothing is removed when `dry_run` is set
+    pub fn prune(&self, dry_run: bool) -> Result<Vec<Extraneous>> {
+        let lockfile = self.read_lockfile()?;
 
-    for version in versions {
-        let version_str = version.0;
-        let version = match Version::parse(version_str) {
-            Ok(version) => version,
-            Err(_) => continue,
+        let graph = ResolvedGraph {
+            packages: lockfile.packages,
+            workspaces: self.workspaces.clone(),
+            sources: BTreeMap::new(),
         };
+        let node_modules = self.root.join("node_modules");
 
-        if version.major == version_req_semver.major
-            || ((version.minor == version_req_semver.minor) && version.major == 0)
-            || ((version.patch == version_req_semver.patch)
-                && version.major == 0
-                && version.minor == 0)
-        {
-            match latest_version.clone() {
-                Some(existing_version) => {
-                    if version > existing_version {
-                        latest_version = Some(version);
-                    }
-                }
-                None => {
-                    latest_version = Some(version);
-                }
-            }
+        if dry_run {
+            Ok(find_extraneous(&node_modules, &graph))
+        } else {
+            prune(&node_modules, &graph, Arc::new(SilentReporter))
         }
     }
 
-    match latest_version {
-        Some(version) => Ok(version.to_string()),
-        None => Err("Could not find version".to_string()),
-    }
-}
-
-#[async_recursion]
-pub async fn get_related_dependencies(
-    package_name: String,
-    version: String,
-    old_dependencies: std::collections::BTreeMap<String, String>,
-) -> Result<std::collections::BTreeMap<String, String>, String> {
-    let contents = read_package_json().unwrap();
-    let new_dependencies: Dependencies = match serde_json::from_str(&contents) {
-        Ok(dependencies) => dependencies,
-        Err(error) => return Err(error.to_string()),
-    };
-
-    if Path::new("blaze.lock").exists() && (new_dependencies.dependencies == old_dependencies) {
-        let mut lock_file = match File::open("blaze.lock") {
-            Ok(lock_file) => lock_file,
-            Err(error) => return Err(error.to_string()),
-        };
-
-        let mut buffer = Vec::new();
-
-        lock_file.read_to_end(&mut buffer).unwrap();
-
-        let deserialized_dependencies: std::collections::BTreeMap<String, String> =
-            bincode::deserialize(&buffer).unwrap();
+    /// Resolves the locked dependencies again, moving the packages installed more than once to
+    /// the version that satisfies the most of the packages depending on them, so that those
+    /// share a single copy, and installs the result. Every package stays within the range it
+    /// is requested with. The lockfile is left as it is when no copy goes away
+    pub async fn dedupe(&self, reporter: Arc<dyn Reporter>) -> Result<DedupeReport> {
+        let lockfile = self.read_lockfile()?;
+        let root_dependencies = self.root_dependencies();
+
+        let preferred_versions = prefer_shared_versions(
+            self.registry().as_ref(),
+            &self.concurrency,
+            &root_dependencies,
+            &lockfile.packages,
+        )
+        .await?;
+
+        let graph = self
+            .resolver(preferred_versions, reporter.clone())?
+            .resolve(&root_dependencies)
+            .await?;
+
+        let before = count_duplicates(&lockfile.packages);
+        let after = count_duplicates(&graph.packages);
+        if after >= before {
+            return Ok(DedupeReport::default());
+        }
 
-        return Ok(deserialized_dependencies);
+        let node_modules = self.root.join("node_modules");
+        let size_before = directory_size(&node_modules);
+
+        self.install(&graph, &node_modules, reporter).await?;
+        self.write_lockfile(&graph.packages)?;
+
+        let changed = graph
+            .packages
+            .iter()
+            .filter_map(|(path, package)| {
+                let old = lockfile.packages.get(path)?;
+                (old.version != package.version)
+                    .then(|| (path.clone(), old.version.clone(), package.version.clone()))
+            })
+            .collect();
+
+        Ok(DedupeReport {
+            duplicates_removed: before - after,
+            bytes_saved: size_before.saturating_sub(directory_size(&node_modules)),
+            changed,
+        })
     }
 
-    let mut all_dependencies = std::collections::BTreeMap::new();
-
-    let url = format!("https://registry.npmjs.org/{}/{}", package_name, version);
-
-    let response = match reqwest::get(&url).await {
-        Ok(response) => response,
-        Err(error) => return Err(error.to_string()),
-    };
-
-    let text = match response.text().await {
-        Ok(text) => text,
-        Err(error) => return Err(error.to_string()),
-    };
-
-    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
-
-    let dependencies = match json["dependencies"].as_object() {
-        Some(dependencies) => dependencies,
-        None => return Ok(all_dependencies),
-    };
+    fn read_lockfile(&self) -> Result<Lockfile> {
+        match Lockfile::read(&self.root) {
+            Some(lockfile) => Ok(lockfile),
+            None => Err(Error::Lockfile {
+                path: LOCKFILE_NAME.to_string(),
+                reason: "not found, run `blaze install` first".to_string(),
+            }),
+        }
+    }
 
-    let mut handles = vec![];
+    /// A resolver for these dependencies, applying their overrides
+    pub fn resolver(
+        &self,
+        preferred_versions: BTreeMap<String, String>,
+        reporter: Arc<dyn Reporter>,
+    ) -> Result<Resolver> {
+        let resolver = Resolver::new(self.registry(), &self.root)
+            .with_overrides(self.overrides()?)
+            .with_preferred_versions(preferred_versions)
+            .with_reporter(reporter)
+            .with_concurrency(self.concurrency.clone());
+
+        Ok(match &self.cache_directory {
+            Some(cache_directory) => resolver.with_cache_directory(cache_directory),
+            None => resolver,
+        })
+    }
 
-    for dependency in dependencies {
-        let dependency_name = dependency.0;
-        let dependency_version = match dependency.1.as_str() {
-            Some(version) => version,
-            None => return Err("Could not get version".into()),
+    /// Fetches the packages of `graph` into the store and links them, along with the
+    /// workspaces, into `node_modules`. Nothing is linked unless every package was fetched.
+    ///
+    /// Only the packages that differ from the `InstallState` of the previous install are
+    /// fetched and linked, and the ones the graph no longer has are removed, along with
+    /// anything else in `node_modules` that the graph does not account for. The state is
+    /// written last, so an interrupted install is picked up where it stopped
+    async fn install(
+        &self,
+        graph: &ResolvedGraph,
+        node_modules: &Path,
+        reporter: Arc<dyn Reporter>,
+    ) -> Result<()> {
+        let graph = ResolvedGraph {
+            packages: graph.packages.clone(),
+            workspaces: self.workspaces.clone(),
+            sources: graph.sources.clone(),
         };
 
-        let cleaned_version = dependency_version.replace(&['^', '~'][..], "");
-
-        let dependency_version = match get_version(dependency_name, &cleaned_version).await {
-            Ok(version) => version,
-            Err(error) => return Err(error),
+        let installed = match &self.selected {
+            Some(selected) => graph.reachable_from(selected),
+            None => graph.clone(),
         };
 
-        all_dependencies.insert(dependency_name.to_string(), dependency_version.to_string());
+        let state = InstallState::read(node_modules);
+        let pending = state.pending(&installed, node_modules);
+
+        let cache_directory = self.cache_directory()?;
+        let fetched = Fetcher::new(
+            self.registry(),
+            Store::in_cache(&cache_directory),
+            &self.root,
+        )
+        .with_cache_directory(&cache_directory)
+        .with_requested_specs(
+            self.root_dependencies()
+                .into_values()
+                .chain(self.overrides()?.to_lock().into_values()),
+        )
+        .with_reporter(reporter.clone())
+        .with_concurrency(self.concurrency.clone())
+        .fetch(&pending)
+        .await?;
+
+        let linker = Linker::new(node_modules)
+            .with_reporter(reporter.clone())
+            .with_concurrency(self.concurrency.clone());
+
+        linker.link(&pending, &fetched).await?;
+        linker.unlink(&state.stale(&graph))?;
+        prune(node_modules, &graph, reporter)?;
+
+        state.updated(&graph, &installed).write(node_modules)
+    }
 
-        let handle = tokio::spawn(get_related_dependencies(
-            dependency_name.clone(),
-            dependency_version.to_string(),
-            old_dependencies.clone(),
-        ));
-        handles.push(handle);
+    fn workspace_paths(&self) -> BTreeMap<String, String> {
+        self.workspaces
+            .iter()
+            .map(|(name, path)| (name.clone(), path.to_string_lossy().to_string()))
+            .collect()
     }
 
-    for handle in handles {
-        let result = handle.await.unwrap();
-        for (name, version) in result? {
-            let version = version.replace(['^', '~'], "");
-            all_dependencies.insert(name, version);
-        }
+    pub fn overrides(&self) -> Result<Overrides> {
+        Overrides::new(
+            &self.overrides,
+            &self.resolutions,
+            &self.root_dependencies(),
+        )
     }
 
-    let mut lock_file = File::create("blaze.lock").unwrap();
+    fn write_lockfile(&self, packages: &BTreeMap<String, LockedPackage>) -> Result<()> {
+        let lockfile = Lockfile {
+            dependencies: self.dependencies.clone(),
+            dev_dependencies: self.dev_dependencies.clone(),
+            workspaces: self.workspace_paths(),
+            overrides: self.overrides()?.to_lock(),
+            packages: packages.clone(),
+        };
 
-    match lock_file.write_all(&bincode::serialize(&all_dependencies).unwrap()) {
-        Ok(_) => (),
-        Err(error) => return Err(error.to_string()),
-    };
+        lockfile.write(&self.root)
+    }
+}
 
-    drop(lock_file);
+/// Whether a version of `name` can satisfy both specifiers. Registry ranges conflict when no
+/// version is in both, other specifiers unless they are the same. Dist-tags such as `latest`
+/// could point anywhere, so they never conflict
+fn specs_intersect(name: &str, a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
 
-    Ok(all_dependencies)
+    match (
+        Specifier::parse(a).registry_package(name),
+        Specifier::parse(b).registry_package(name),
+    ) {
+        (Some((a_name, a_range)), Some((b_name, b_range))) => {
+            match (VersionRange::parse(&a_range), VersionRange::parse(&b_range)) {
+                (Ok(a_range), Ok(b_range)) => a_name == b_name && a_range.intersects(&b_range),
+                _ => a_name == b_name,
+            }
+        }
+        _ => false,
+    }
 }
 
-pub async fn download_dependency(
-    package_name: String,
-    version: String,
-) -> Result<(), Box<dyn Error>> {
-    let url = format!(
-        "https://registry.npmjs.org/{}/-/{}-{}.tgz",
-        package_name, package_name, version
-    );
+/// Rewrites a spec that points at a local directory or tarball relative to `directory`, the
+/// workspace declaring it, so that it is relative to the project root. Other specs are returned
+/// as they are
+fn rebase_local_spec(spec: &str, directory: &Path) -> String {
+    let (prefix, path) = match Specifier::parse(spec) {
+        Specifier::Link(path) => ("link", path),
+        Specifier::Directory(path) | Specifier::LocalTarball(path) => ("file", path),
+        _ => return spec.to_string(),
+    };
 
-    let client = reqwest::Client::new();
-    let mut resp = client.get(url).send().await?;
+    if path.is_absolute() {
+        return spec.to_string();
+    }
 
-    let mut file = File::create(format!("node_modules/{}-{}.tgz", package_name, version))?;
+    let mut rebased = PathBuf::new();
+    for component in directory.join(path).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(rebased.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                rebased.pop();
+            }
+            component => rebased.push(component),
+        }
+    }
 
-    while let Some(chunk) = resp.chunk().await? {
-        file.write_all(&chunk)?;
+    if rebased.as_os_str().is_empty() {
+        rebased.push(".");
     }
 
-    Ok(())
+    format!(
+        "{}:{}",
+        prefix,
+        rebased.to_string_lossy().replace('\\', "/")
+    )
 }
 
-fn find_tgz_file_with_word(directory: &str, target_word: &str) -> PathBuf {
-    let matching_files = PathBuf::new();
+pub async fn get_latest_version(registry: &dyn Registry, package_name: &str) -> Result<String> {
+    let json = registry.fetch_packument(package_name).await?;
 
-    if let Ok(entries) = fs::read_dir(directory) {
-        for entry in entries.flatten() {
-            let file_name = entry.file_name();
-            if let Some(file_name_str) = file_name.to_str() {
-                if file_name_str.ends_with(".tgz") && file_name_str.contains(target_word) {
-                    return entry.path();
-                }
-            }
-        }
+    match json["dist-tags"]["latest"].as_str() {
+        Some(version) => Ok(version.to_string()),
+        None => Err(Error::NoMatchingVersion {
+            name: package_name.to_string(),
+            spec: "latest".to_string(),
+        }),
     }
-
-    matching_files
 }
 
-pub async fn extract_dependency(package_name: String) -> Result<(), std::io::Error> {
-    let path = find_tgz_file_with_word("node_modules", &package_name);
-    let extraction_path = format!("node_modules/{}", package_name);
+pub async fn get_version(
+    registry: &dyn Registry,
+    package_name: &str,
+    version_req: &str,
+) -> Result<String> {
+    let json = registry.fetch_packument(package_name).await?;
 
-    if !Path::new(&extraction_path).exists() {
-        fs::create_dir_all(&extraction_path)?;
+    max_satisfying_version(&json, version_req)
+}
+
+/// Picks the highest version in a packument that satisfies `version_req`, which may also be a
+/// dist-tag such as `latest` or `next`
+pub fn max_satisfying_version(packument: &Value, version_req: &str) -> Result<String> {
+    if let Some(version) = packument["dist-tags"][version_req].as_str() {
+        return Ok(version.to_string());
     }
 
-    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&path)?));
+    let name = packument["name"].as_str().unwrap_or_default();
 
-    archive.unpack(&extraction_path)?;
+    let versions = match packument["versions"].as_object() {
+        Some(versions) => versions,
+        None => return Err(Error::manifest(name, "missing `versions`")),
+    };
 
-    let pkg_path = format!("{}/package", extraction_path);
-    let pkg_path = Path::new(&pkg_path);
+    let range = VersionRange::parse(version_req)?;
 
-    copy_folder_contents(pkg_path, &PathBuf::from(extraction_path))?;
+    let versions: Vec<Version> = versions
+        .keys()
+        .filter_map(|version| Version::parse(version).ok())
+        .collect();
 
-    fs::remove_dir_all(pkg_path)?;
-    fs::remove_file(&path)?;
+    match range.max_satisfying(&versions) {
+        Some(version) => Ok(version.to_string()),
+        None => Err(Error::NoMatchingVersion {
+            name: name.to_string(),
+            spec: version_req.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebases_local_specs_of_workspaces() {
+        let workspace = Path::new("packages/b");
+
+        assert_eq!(rebase_local_spec("file:../../lib", workspace), "file:lib");
+        assert_eq!(
+            rebase_local_spec("link:../shared", workspace),
+            "link:packages/shared"
+        );
+        assert_eq!(
+            rebase_local_spec("./vendor/x.tgz", workspace),
+            "file:packages/b/vendor/x.tgz"
+        );
+        assert_eq!(rebase_local_spec("file:../..", workspace), "file:.");
+        assert_eq!(
+            rebase_local_spec("file:/opt/lib", workspace),
+            "file:/opt/lib"
+        );
+        assert_eq!(rebase_local_spec("^1.2.0", workspace), "^1.2.0");
+        assert_eq!(
+            rebase_local_spec("github:user/repo", workspace),
+            "github:user/repo"
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn keeps_the_first_range_of_dependencies_shared_with_workspaces() {
+        let workspace = |name: &str| Workspace {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            path: Path::new("packages").join(name),
+            directory: Path::new("/project/packages").join(name),
+        };
+        let declaring = |dependencies: &[(&str, &str)]| Dependencies {
+            dependencies: dependencies
+                .iter()
+                .map(|(name, spec)| (name.to_string(), spec.to_string()))
+                .collect(),
+            ..Dependencies::default()
+        };
+        let (a, b) = (workspace("a"), workspace("b"));
+        let workspace_names = BTreeSet::from(["a", "b"]);
+
+        let mut root = declaring(&[("shared", "^1.0.0")]);
+        let mut declared_by = BTreeMap::from([("shared".to_string(), "the project".to_string())]);
+
+        root.merge_workspace_dependencies(
+            &a,
+            declaring(&[
+                ("shared", "^1.2.0"),
+                ("lib", "file:../lib"),
+                ("b", "workspace:*"),
+            ]),
+            &workspace_names,
+            &mut declared_by,
+        )
+        .unwrap();
+        assert_eq!(root.dependencies["shared"], "^1.0.0");
+        assert_eq!(root.dependencies["lib"], "file:packages/lib");
+
+        // dependencies on the workspaces themselves are served by their links
+        root.merge_workspace_dependencies(
+            &b,
+            declaring(&[
+                ("b", "^1.0.0"),
+                ("lib", "file:../lib"),
+                ("latest", "latest"),
+            ]),
+            &workspace_names,
+            &mut declared_by,
+        )
+        .unwrap();
+
+        let conflict = root.merge_workspace_dependencies(
+            &b,
+            declaring(&[("shared", "^2.0.0")]),
+            &workspace_names,
+            &mut declared_by,
+        );
+        assert_eq!(
+            conflict.unwrap_err().to_string(),
+            "the project and b depend on incompatible versions of shared: ^1.0.0 and ^2.0.0"
+        );
+
+        let conflict = root.merge_workspace_dependencies(
+            &b,
+            declaring(&[("lib", "file:../other")]),
+            &workspace_names,
+            &mut declared_by,
+        );
+        assert_eq!(
+            conflict.unwrap_err().to_string(),
+            "a and b depend on incompatible versions of lib: file:packages/lib and file:packages/other"
+        );
+    }
 }
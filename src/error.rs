@@ -0,0 +1,254 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while resolving, fetching or installing packages
+#[derive(Debug)]
+pub enum Error {
+    /// The registry does not know a package with this name
+    PackageNotFound {
+        name: String,
+    },
+    /// No published version or dist-tag of the package satisfies `spec`
+    NoMatchingVersion {
+        name: String,
+        spec: String,
+    },
+    /// A version range, override or other specifier that cannot be parsed
+    InvalidSpec {
+        spec: String,
+        reason: String,
+    },
+    /// A dependency or workspace name that is not a valid package name, such as one that would be
+    /// installed outside of `node_modules`
+    InvalidName {
+        name: String,
+    },
+    /// A request failed or the server answered with an error status
+    Network {
+        url: String,
+        reason: String,
+    },
+    /// A downloaded tarball does not match its integrity hash. `actual` is `None` when none of
+    /// the hashes in `expected` use a supported algorithm
+    Integrity {
+        package: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    /// A `package.json` or registry manifest that is missing, malformed or lacks a field
+    Manifest {
+        path: String,
+        reason: String,
+    },
+    /// `blaze.lock` or the lockfile of another package manager cannot be read or written
+    Lockfile {
+        path: String,
+        reason: String,
+    },
+    /// A git command failed, or the requested commit does not exist
+    Git {
+        url: String,
+        reason: String,
+    },
+    /// The `workspaces` of the project are misconfigured or a filter matches none of them
+    Workspace {
+        reason: String,
+    },
+    /// `package.json` does not define the script
+    MissingScript {
+        name: String,
+    },
+    /// A lifecycle script run by an install exited with a nonzero code
+    ScriptFailed {
+        name: String,
+        code: i32,
+    },
+    /// A package does not provide the binary to run
+    Binary {
+        package: String,
+        reason: String,
+    },
+    /// A script, binary or git could not be started
+    Command {
+        command: String,
+        source: io::Error,
+    },
+    /// Packages failed to resolve, download or link. An install keeps going after a package
+    /// fails, so that every failure is reported at once
+    Install {
+        failures: Vec<PackageFailure>,
+    },
+    /// The project has no `blaze.lock` to inspect
+    MissingLockfile,
+    /// `blaze.lock` does not lock the package, or none of its versions match the requested one
+    NotInstalled {
+        package: String,
+    },
+    /// The platform does not provide a cache directory
+    NoCacheDirectory,
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+/// A package an install could not get into `node_modules`
+#[derive(Debug)]
+pub struct PackageFailure {
+    /// The package as it was requested, such as `name@range` or `name@version`
+    pub package: String,
+    pub stage: Stage,
+    pub error: Error,
+}
+
+/// The phase of an install a package failed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Resolve,
+    Fetch,
+    Link,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Resolve => write!(f, "resolve"),
+            Stage::Fetch => write!(f, "download"),
+            Stage::Link => write!(f, "link"),
+        }
+    }
+}
+
+impl Error {
+    /// Builds a closure wrapping an `io::Error` together with the path it happened on, for use
+    /// with `map_err`
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+
+    pub fn network(url: &str, reason: impl fmt::Display) -> Error {
+        Error::Network {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn manifest(path: impl fmt::Display, reason: impl fmt::Display) -> Error {
+        Error::Manifest {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// A suggestion on how to fix the error, printed by the CLI below the error itself
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Error::PackageNotFound { .. } => {
+                Some("Check the spelling of the package name".to_string())
+            }
+            Error::NoMatchingVersion { name, .. } => Some(format!(
+                "Run `blaze outdated` or check the versions published for {}",
+                name
+            )),
+            Error::Network { .. } => Some("Check your internet connection".to_string()),
+            Error::Integrity { .. } => Some(
+                "The tarball changed since it was locked, delete blaze.lock to resolve it again"
+                    .to_string(),
+            ),
+            Error::Lockfile { .. } => {
+                Some("Delete the lockfile and run `blaze install` to recreate it".to_string())
+            }
+            Error::Git { .. } => {
+                Some("Make sure git is installed and the repository is reachable".to_string())
+            }
+            Error::MissingScript { .. } => {
+                Some("Run `blaze run` to list the available scripts".to_string())
+            }
+            Error::Install { failures } => failures.iter().find_map(|failure| failure.error.hint()),
+            Error::MissingLockfile | Error::NotInstalled { .. } => {
+                Some("Run `blaze install` first".to_string())
+            }
+            Error::ScriptFailed { .. } => {
+                Some("Pass --ignore-scripts to install without running scripts".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PackageNotFound { name } => write!(f, "Package {} was not found", name),
+            Error::NoMatchingVersion { name, spec } => {
+                write!(f, "Could not find a version of {} matching {}", name, spec)
+            }
+            Error::InvalidSpec { spec, reason } => write!(f, "Invalid `{}`: {}", spec, reason),
+            Error::InvalidName { name } => write!(f, "Invalid package name `{}`", name),
+            Error::Network { url, reason } => write!(f, "Could not fetch {}: {}", url, reason),
+            Error::Integrity {
+                package,
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "Integrity check failed for {}: expected {} but got {}",
+                package, expected, actual
+            ),
+            Error::Integrity {
+                package,
+                expected,
+                actual: None,
+            } => write!(f, "Unsupported integrity `{}` for {}", expected, package),
+            Error::Manifest { path, reason } => write!(f, "Invalid {}: {}", path, reason),
+            Error::Lockfile { path, reason } => write!(f, "Invalid {}: {}", path, reason),
+            Error::Git { url, reason } => write!(f, "Could not fetch {}: {}", url, reason),
+            Error::Workspace { reason } => write!(f, "{}", reason),
+            Error::MissingScript { name } => write!(f, "Missing script: \"{}\"", name),
+            Error::ScriptFailed { name, code } => {
+                write!(f, "Script \"{}\" exited with code {}", name, code)
+            }
+            Error::Binary { package, reason } => write!(f, "{} {}", package, reason),
+            Error::Command { command, source } => {
+                write!(f, "Failed to run `{}`: {}", command, source)
+            }
+            Error::Install { failures } => {
+                let packages = if failures.len() == 1 {
+                    "package"
+                } else {
+                    "packages"
+                };
+                write!(f, "Could not install {} {}:", failures.len(), packages)?;
+
+                for failure in failures {
+                    write!(
+                        f,
+                        "\n  {}: could not {}: {}",
+                        failure.package, failure.stage, failure.error
+                    )?;
+                }
+
+                Ok(())
+            }
+            Error::MissingLockfile => write!(f, "No blaze.lock found"),
+            Error::NotInstalled { package } => {
+                write!(f, "{} is not part of the dependency tree", package)
+            }
+            Error::NoCacheDirectory => write!(f, "Failed to get cache directory"),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Command { source, .. } | Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
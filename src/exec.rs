@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::dependencies::{get_version, Dependencies};
+use crate::error::{Error, Result};
+use crate::linker::package_binaries;
+use crate::reporter::Reporter;
+use crate::scripts::path_with_bin_directories;
+use crate::utils::split_package_spec;
+
+/// Tells apart the staging prefixes of packages installed at the same time
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs a package binary with the given arguments, returning its exit code.
+///
+/// Binaries installed in a `node_modules/.bin` directory of `root` or one of its ancestors are run
+/// directly. Otherwise `package` (which defaults to `command`) is installed into a prefix inside
+/// the blaze cache directory, and the binary is run from there. That install uses the registry,
+/// concurrency and cache directory of `installer`, and its progress goes to `reporter`
+pub async fn exec(
+    root: &Path,
+    installer: Dependencies,
+    reporter: Arc<dyn Reporter>,
+    command: &str,
+    package: Option<&str>,
+    args: &[String],
+) -> Result<i32> {
+    let (command_name, _) = split_package_spec(command);
+
+    if package.is_none() {
+        if let Some(bin_path) = find_local_binary(root, command_name) {
+            return run_binary(root, &bin_path, args, None);
+        }
+    }
+
+    let (package_name, version_req) = split_package_spec(package.unwrap_or(command));
+    let version = get_version(
+        installer.registry().as_ref(),
+        package_name,
+        version_req.unwrap_or("latest"),
+    )
+    .await?;
+
+    let cache_dir = installer.cache_directory()?.join("exec");
+    let prefix = cache_dir.join(format!("{}@{}", package_name.replace('/', "+"), version));
+    let node_modules = prefix.join("node_modules");
+
+    // the package is installed into a staging prefix that is then renamed into place, so an
+    // interrupted install never looks complete
+    if !prefix.is_dir() {
+        let staging = cache_dir.join(format!(
+            ".staging-{}-{}",
+            std::process::id(),
+            STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut installer = installer;
+        installer.dependencies = BTreeMap::new();
+        installer
+            .dependencies
+            .insert(package_name.to_string(), version.clone());
+
+        if let Err(error) = installer
+            .install_to(&staging.join("node_modules"), reporter)
+            .await
+        {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(error);
+        }
+
+        if let Err(error) = fs::rename(&staging, &prefix) {
+            let _ = fs::remove_dir_all(&staging);
+
+            // another exec may have installed the same package in the meantime
+            if !prefix.is_dir() {
+                return Err(Error::io(&prefix)(error));
+            }
+        }
+    }
+
+    let bin_name = find_package_binary(&node_modules, package_name, command_name)?;
+    let bin_directory = node_modules.join(".bin");
+
+    run_binary(
+        root,
+        &bin_path(&bin_directory, &bin_name),
+        args,
+        Some(&bin_directory),
+    )
+}
+
+fn find_local_binary(root: &Path, bin_name: &str) -> Option<PathBuf> {
+    root.ancestors()
+        .map(|directory| bin_path(&directory.join("node_modules").join(".bin"), bin_name))
+        .find(|path| path.exists())
+}
+
+/// Picks the binary to run from a package, preferring the one named `command_name` and falling
+/// back to the only binary the package declares
+fn find_package_binary(
+    node_modules: &Path,
+    package_name: &str,
+    command_name: &str,
+) -> Result<String> {
+    let package_json_path = node_modules.join(package_name).join("package.json");
+    let package_json = match std::fs::read_to_string(&package_json_path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        Err(error) => {
+            return Err(Error::Io {
+                path: package_json_path,
+                source: error,
+            })
+        }
+    };
+
+    let binaries = package_binaries(package_name, &package_json);
+    let unscoped_command = command_name.rsplit('/').next().unwrap_or(command_name);
+
+    if binaries.contains_key(unscoped_command) {
+        return Ok(unscoped_command.to_string());
+    }
+
+    match binaries.keys().next() {
+        Some(bin_name) if binaries.len() == 1 => Ok(bin_name.clone()),
+        Some(_) => Err(Error::Binary {
+            package: package_name.to_string(),
+            reason: format!(
+                "has several binaries, pick one of: {}",
+                binaries.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }),
+        None => Err(Error::Binary {
+            package: package_name.to_string(),
+            reason: "does not have any binaries".to_string(),
+        }),
+    }
+}
+
+#[cfg(windows)]
+fn bin_path(bin_directory: &Path, bin_name: &str) -> PathBuf {
+    bin_directory.join(format!("{}.cmd", bin_name))
+}
+
+#[cfg(not(windows))]
+fn bin_path(bin_directory: &Path, bin_name: &str) -> PathBuf {
+    bin_directory.join(bin_name)
+}
+
+fn run_binary(
+    root: &Path,
+    bin_path: &Path,
+    args: &[String],
+    extra_path: Option<&Path>,
+) -> Result<i32> {
+    let mut paths: Vec<PathBuf> = extra_path.into_iter().map(Path::to_path_buf).collect();
+    paths.extend(env::split_paths(&path_with_bin_directories(root)?));
+
+    // the paths come from `PATH` and `node_modules/.bin` directories, which can always be joined
+    let path = env::join_paths(paths).unwrap_or_default();
+
+    let status = match Command::new(bin_path).args(args).env("PATH", path).status() {
+        Ok(status) => status,
+        Err(error) => {
+            return Err(Error::Command {
+                command: bin_path.display().to_string(),
+                source: error,
+            })
+        }
+    };
+
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,167 @@
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::lockfile::{find_installed, installed_name, LockedPackage, Lockfile};
+use crate::specifier::Specifier;
+use crate::utils::{read_package_json, JsonStyle};
+
+pub const PACKAGE_LOCK_NAME: &str = "package-lock.json";
+
+/// The fields of the root `package.json` that npm copies into the `""` entry of `packages`
+const ROOT_FIELDS: [&str; 9] = [
+    "name",
+    "version",
+    "license",
+    "workspaces",
+    "bin",
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+/// Converts `blaze.lock` into a `package-lock.json` with `lockfileVersion` 3, where `packages`
+/// is keyed by the path each package is installed at
+pub fn package_lock(lockfile: &Lockfile, root: &Path, package_json: &Value) -> Value {
+    let mut packages = Map::new();
+
+    let mut root_entry = Map::new();
+    for field in ROOT_FIELDS {
+        if let Some(value) = package_json.get(field) {
+            root_entry.insert(field.to_string(), value.clone());
+        }
+    }
+    packages.insert(String::new(), Value::Object(root_entry));
+
+    let production_packages = production_packages(lockfile);
+
+    for (installed_at, package) in &lockfile.packages {
+        let path = format!("node_modules/{}", installed_at);
+
+        match Specifier::parse(&package.resolved) {
+            Specifier::Link(directory) | Specifier::Directory(directory) => {
+                let directory = directory
+                    .to_string_lossy()
+                    .trim_start_matches("./")
+                    .to_string();
+
+                packages.insert(path, json!({ "resolved": directory, "link": true }));
+                packages.insert(directory, Value::Object(package_entry("", package, false)));
+            }
+            _ => {
+                let mut entry = package_entry(installed_name(installed_at), package, true);
+                if !production_packages.contains(installed_at) {
+                    entry.insert("dev".to_string(), Value::Bool(true));
+                }
+
+                packages.insert(path, Value::Object(entry));
+            }
+        };
+    }
+
+    for (name, directory) in &lockfile.workspaces {
+        let workspace_json = read_package_json(&root.join(directory))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+            .unwrap_or_default();
+
+        let mut entry = Map::new();
+        for field in ROOT_FIELDS {
+            if let Some(value) = workspace_json.get(field) {
+                entry.insert(field.to_string(), value.clone());
+            }
+        }
+
+        packages.insert(
+            format!("node_modules/{}", name),
+            json!({ "resolved": directory, "link": true }),
+        );
+        packages.insert(directory.clone(), Value::Object(entry));
+    }
+
+    let mut package_lock = Map::new();
+    for field in ["name", "version"] {
+        if let Some(value) = package_json.get(field) {
+            package_lock.insert(field.to_string(), value.clone());
+        }
+    }
+    package_lock.insert("lockfileVersion".to_string(), json!(3));
+    package_lock.insert("requires".to_string(), Value::Bool(true));
+    package_lock.insert("packages".to_string(), Value::Object(packages));
+
+    Value::Object(package_lock)
+}
+
+/// Writes the `package-lock.json` for `lockfile` next to the `package.json` in `root`
+pub fn write_package_lock(lockfile: &Lockfile, root: &Path) -> Result<()> {
+    let package_json: Value = match serde_json::from_str(&read_package_json(root)?) {
+        Ok(package_json) => package_json,
+        Err(error) => return Err(Error::manifest(root.join("package.json").display(), error)),
+    };
+
+    let package_lock = package_lock(lockfile, root, &package_json);
+
+    let contents = match JsonStyle::default().to_string(&package_lock) {
+        Ok(contents) => contents,
+        Err(error) => {
+            return Err(Error::Lockfile {
+                path: PACKAGE_LOCK_NAME.to_string(),
+                reason: error.to_string(),
+            })
+        }
+    };
+
+    let path = root.join(PACKAGE_LOCK_NAME);
+    fs::write(&path, contents).map_err(Error::io(&path))
+}
+
+fn package_entry(name: &str, package: &LockedPackage, include_source: bool) -> Map<String, Value> {
+    let mut entry = Map::new();
+
+    // aliased packages are installed under a different name than their own
+    if package.name != name {
+        entry.insert("name".to_string(), json!(package.name));
+    }
+    entry.insert("version".to_string(), json!(package.version));
+
+    if include_source {
+        entry.insert("resolved".to_string(), json!(package.resolved));
+        if !package.integrity.is_empty() {
+            entry.insert("integrity".to_string(), json!(package.integrity));
+        }
+    }
+
+    if !package.dependencies.is_empty() {
+        entry.insert("dependencies".to_string(), json!(package.dependencies));
+    }
+
+    entry
+}
+
+/// The install paths of the packages reachable from the `dependencies` of the root
+/// `package.json`. npm marks every other package with `"dev": true`
+fn production_packages(lockfile: &Lockfile) -> BTreeSet<String> {
+    let mut visited = BTreeSet::new();
+    let mut queue: Vec<String> = lockfile.dependencies.keys().cloned().collect();
+
+    while let Some(path) = queue.pop() {
+        let package = match lockfile.packages.get(&path) {
+            Some(package) => package,
+            None => continue,
+        };
+
+        if visited.insert(path.clone()) {
+            queue.extend(
+                package
+                    .dependencies
+                    .keys()
+                    .filter_map(|name| find_installed(&lockfile.packages, &path, name)),
+            );
+        }
+    }
+
+    visited
+}
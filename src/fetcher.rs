@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Handle;
+
+use crate::concurrency::Concurrency;
+use crate::error::{Error, PackageFailure, Result, Stage};
+use crate::lockfile::LockedPackage;
+use crate::registry::{Registry, Tarball};
+use crate::reporter::{Event, Reporter, SilentReporter};
+use crate::resolver::ResolvedGraph;
+use crate::sources::{fetch_source, sources_directory, FetchedSource};
+use crate::specifier::Specifier;
+use crate::store::Store;
+use crate::utils::joined;
+
+/// The directories holding the contents of fetched packages, keyed by the path they are
+/// installed at
+pub type FetchedPackages = BTreeMap<String, PathBuf>;
+
+/// Downloads the registry packages of a `ResolvedGraph` into the store. Directories, tarball
+/// urls and git repositories are staged by `sources` instead, unless the graph already holds
+/// them from resolving, and linked packages need no fetching
+#[derive(Clone)]
+pub struct Fetcher {
+    registry: Arc<dyn Registry>,
+    store: Store,
+    /// The project root, which local paths are relative to
+    root: PathBuf,
+    /// The specifiers the project requests packages with
+    requested: Vec<String>,
+    reporter: Arc<dyn Reporter>,
+    concurrency: Concurrency,
+    /// The blaze cache that sources are fetched into, the default one when unset
+    cache_directory: Option<PathBuf>,
+}
+
+impl Fetcher {
+    pub fn new(registry: Arc<dyn Registry>, store: Store, root: &Path) -> Fetcher {
+        Fetcher {
+            registry,
+            store,
+            root: root.to_path_buf(),
+            requested: Vec::new(),
+            reporter: Arc::new(SilentReporter),
+            concurrency: Concurrency::default(),
+            cache_directory: None,
+        }
+    }
+
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Fetcher {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Adds the specifiers the project itself requests packages with, such as its dependencies
+    /// and overrides. Registry packages and packages depending on a tarball url are both locked
+    /// with a url, so a package is only downloaded from its url rather than the registry when
+    /// one of these specifiers, or a dependency in the graph, asks for that url
+    pub fn with_requested_specs(mut self, specs: impl IntoIterator<Item = String>) -> Fetcher {
+        self.requested.extend(specs);
+        self
+    }
+
+    /// Downloads hold a network slot, and unpacking into the store a file system slot
+    pub fn with_concurrency(mut self, concurrency: Concurrency) -> Fetcher {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_cache_directory(mut self, cache_directory: &Path) -> Fetcher {
+        self.cache_directory = Some(cache_directory.to_path_buf());
+        self
+    }
+
+    /// Fetches every package of `graph` that is not in the store yet. The other packages are
+    /// still fetched when one fails, and every failure is returned together as an
+    /// `Error::Install`. Packages only enter the store once they are complete, so a failed
+    /// fetch leaves nothing half-written behind
+    pub async fn fetch(&self, graph: &ResolvedGraph) -> Result<FetchedPackages> {
+        let packages: Vec<(String, LockedPackage)> = graph
+            .packages
+            .iter()
+            .filter(|(_, package)| {
+                !matches!(Specifier::parse(&package.resolved), Specifier::Link(_))
+            })
+            .map(|(name, package)| (name.clone(), package.clone()))
+            .collect();
+
+        let direct_urls: Arc<BTreeSet<String>> = Arc::new(
+            self.requested
+                .iter()
+                .chain(
+                    graph
+                        .packages
+                        .values()
+                        .flat_map(|package| package.dependencies.values()),
+                )
+                .filter_map(|spec| match Specifier::parse(spec) {
+                    Specifier::RemoteTarball(url) => Some(url),
+                    _ => None,
+                })
+                .collect(),
+        );
+
+        self.reporter.report(Event::FetchStarted {
+            packages: packages.len(),
+        });
+
+        let mut handles = Vec::new();
+
+        for (name, package) in packages {
+            let fetcher = self.clone();
+            let direct_urls = direct_urls.clone();
+            let source = graph.sources.get(&name).cloned();
+
+            handles.push(tokio::spawn(async move {
+                let result = fetcher.fetch_package(&package, source, &direct_urls).await;
+
+                if let Ok((_, bytes, cached)) = &result {
+                    fetcher.reporter.report(Event::Fetched {
+                        name: name.clone(),
+                        version: package.version.clone(),
+                        bytes: *bytes,
+                        cached: *cached,
+                    });
+                }
+
+                (name, package.version, result)
+            }));
+        }
+
+        let mut fetched = FetchedPackages::new();
+        let mut failures = Vec::new();
+
+        for handle in handles {
+            match joined(handle.await) {
+                (name, _, Ok((directory, _, _))) => {
+                    fetched.insert(name, directory);
+                }
+                (name, version, Err(error)) => failures.push(PackageFailure {
+                    package: format!("{}@{}", name, version),
+                    stage: Stage::Fetch,
+                    error,
+                }),
+            }
+        }
+
+        self.reporter.report(Event::FetchFinished);
+
+        if !failures.is_empty() {
+            return Err(Error::Install { failures });
+        }
+
+        Ok(fetched)
+    }
+
+    /// Fetches a single package, returning the directory holding its contents, the number of
+    /// bytes downloaded and whether it was already in the store. `source` is what resolving
+    /// fetched for the package, if anything, and `direct_urls` the tarball urls that are not
+    /// served by the registry
+    async fn fetch_package(
+        &self,
+        package: &LockedPackage,
+        source: Option<FetchedSource>,
+        direct_urls: &BTreeSet<String>,
+    ) -> Result<(PathBuf, u64, bool)> {
+        if let Some(source) = source.filter(|source| source.resolved == package.resolved) {
+            return Ok((source.directory, 0, false));
+        }
+
+        match Specifier::parse(&package.resolved) {
+            Specifier::RemoteTarball(url) if direct_urls.contains(&url) => {
+                self.fetch_source(&Specifier::RemoteTarball(url), package)
+                    .await
+            }
+            Specifier::RemoteTarball(_) | Specifier::Registry(_) | Specifier::Alias { .. } => {
+                if self.store.contains(package) {
+                    return Ok((self.store.package_directory(package), 0, true));
+                }
+
+                // the tarball is unpacked while it downloads, so both slots are held until the
+                // package is in the store
+                let network_permit = self.concurrency.network().await;
+                let tarball = self
+                    .registry
+                    .fetch_tarball(&package.name, &package.version, &package.resolved)
+                    .await?;
+
+                let io_permit = self.concurrency.io().await;
+                let store = self.store.clone();
+                let package = package.clone();
+                let tarball = BlockingReader {
+                    tarball,
+                    runtime: Handle::current(),
+                    failure: Arc::default(),
+                };
+                let failure = tarball.failure.clone();
+
+                let added = joined(
+                    tokio::task::spawn_blocking(move || {
+                        let _permits = (network_permit, io_permit);
+                        store.add(&package, tarball)
+                    })
+                    .await,
+                );
+
+                match added {
+                    Ok((directory, bytes)) => Ok((directory, bytes, false)),
+                    Err(error) => Err(failure.lock().unwrap().take().unwrap_or(error)),
+                }
+            }
+            specifier => self.fetch_source(&specifier, package).await,
+        }
+    }
+
+    /// Fetches a package that is not on the registry through `sources`
+    async fn fetch_source(
+        &self,
+        specifier: &Specifier,
+        package: &LockedPackage,
+    ) -> Result<(PathBuf, u64, bool)> {
+        let _permit = self.concurrency.source(specifier).await;
+        let sources = sources_directory(self.cache_directory.as_deref())?;
+        let source = fetch_source(specifier, &package.integrity, &self.root, &sources).await?;
+
+        Ok((source.directory, 0, false))
+    }
+}
+
+/// Reads a tarball that is still downloading from a blocking thread, such as the one unpacking
+/// it, by waiting on the runtime for each chunk
+struct BlockingReader {
+    tarball: Tarball,
+    runtime: Handle,
+    /// The error the download failed with, which is reported instead of the failed unpack
+    failure: Arc<Mutex<Option<Error>>>,
+}
+
+impl Read for BlockingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.runtime.block_on(self.tarball.read(buf)) {
+            Err(error) if error.get_ref().map_or(false, |inner| inner.is::<Error>()) => {
+                let message = error.to_string();
+
+                if let Some(Ok(failure)) = error.into_inner().map(|inner| inner.downcast()) {
+                    *self.failure.lock().unwrap() = Some(*failure);
+                }
+
+                Err(io::Error::new(io::ErrorKind::Other, message))
+            }
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use semver::Version;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::lockfile::{installed_name, parent_path, LockedPackage};
+use crate::reporter::{Event, Reporter};
+use crate::specifier::Specifier;
+use crate::utils::split_package_spec;
+use crate::version_range::satisfies;
+
+/// The lockfile of another package manager, read when a project has no `blaze.lock` yet so that
+/// switching to blaze keeps the versions it locked
+#[derive(Debug, Clone, Default)]
+pub struct ImportedLockfile {
+    pub file_name: &'static str,
+    /// Every locked package keyed by the name it is installed under. yarn and pnpm lock several
+    /// versions of the same package, so names may repeat
+    pub packages: Vec<(String, LockedPackage)>,
+    /// The versions locked for the dependencies of the root `package.json`
+    pub root_versions: BTreeMap<String, String>,
+    /// The versions installed at each path of `node_modules`, keyed like the packages of a
+    /// `ResolvedGraph`, such as `a` or `a/node_modules/b`. Only `package-lock.json` records
+    /// where packages are installed
+    pub installed: BTreeMap<String, String>,
+}
+
+impl ImportedLockfile {
+    /// Reads the `package-lock.json` (v2 and v3), `yarn.lock` (v1) or `pnpm-lock.yaml` in
+    /// `root`, in that order, returning `None` when there is none of them
+    pub fn read(
+        root: &Path,
+        root_dependencies: &BTreeMap<String, String>,
+    ) -> Result<Option<ImportedLockfile>> {
+        let parsers: [(&'static str, ImportParser); 3] = [
+            ("package-lock.json", parse_package_lock),
+            ("yarn.lock", parse_yarn_lock),
+            ("pnpm-lock.yaml", parse_pnpm_lock),
+        ];
+
+        for (file_name, parse) in parsers {
+            let path = root.join(file_name);
+            if !path.exists() {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path).map_err(Error::io(&path))?;
+
+            return match parse(&contents, root_dependencies) {
+                Ok((packages, root_versions, installed)) => Ok(Some(ImportedLockfile {
+                    file_name,
+                    packages,
+                    root_versions,
+                    installed,
+                })),
+                Err(reason) => Err(Error::Lockfile {
+                    path: file_name.to_string(),
+                    reason,
+                }),
+            };
+        }
+
+        Ok(None)
+    }
+
+    /// The versions to prefer while resolving, keyed by install path. At the top of
+    /// `node_modules`, that is the locked version for root dependencies, then the version
+    /// installed there. When the lockfile does not record where packages are installed, the
+    /// version satisfying the most locked dependents is taken, since that is the one a
+    /// hoisting install puts at the top. Nested copies keep their versions at the paths they
+    /// were installed at, and a warning names the versions that no path keeps
+    pub fn preferred_versions(&self, reporter: &dyn Reporter) -> BTreeMap<String, String> {
+        let mut locked_versions: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+        for (name, package) in &self.packages {
+            if Version::parse(&package.version).is_err() {
+                continue;
+            }
+
+            let versions = locked_versions.entry(name).or_default();
+            if !versions.contains(&package.version.as_str()) {
+                versions.push(&package.version);
+            }
+        }
+
+        let mut preferred_versions: BTreeMap<String, String> = self
+            .installed
+            .iter()
+            .filter(|(path, _)| parent_path(path).is_some())
+            .map(|(path, version)| (path.clone(), version.clone()))
+            .collect();
+
+        for (name, versions) in locked_versions {
+            let preferred = match self
+                .root_versions
+                .get(name)
+                .or_else(|| self.installed.get(name))
+            {
+                Some(version) => version.as_str(),
+                None => self.most_depended_on(name, &versions),
+            };
+
+            let dropped: Vec<&str> = versions
+                .iter()
+                .copied()
+                .filter(|version| {
+                    *version != preferred
+                        && !preferred_versions
+                            .iter()
+                            .any(|(path, nested)| installed_name(path) == name && nested == version)
+                })
+                .collect();
+
+            if !dropped.is_empty() {
+                reporter.report(Event::Warning {
+                    message: format!(
+                        "{} also locks {} {}, only {} is kept and the packages outside of its \
+                         range get the newest version within it",
+                        self.file_name,
+                        name,
+                        dropped.join(", "),
+                        preferred
+                    ),
+                });
+            }
+
+            preferred_versions.insert(name.to_string(), preferred.to_string());
+        }
+
+        preferred_versions.extend(self.root_versions.clone());
+        preferred_versions
+    }
+
+    /// The version of `name` within the ranges of the most locked packages depending on it,
+    /// preferring the highest one on a tie
+    fn most_depended_on<'a>(&self, name: &str, versions: &[&'a str]) -> &'a str {
+        let dependents = |version: &str| {
+            self.packages
+                .iter()
+                .filter_map(|(_, package)| package.dependencies.get(name))
+                .filter(|range| satisfies(version, range))
+                .count()
+        };
+
+        versions
+            .iter()
+            .copied()
+            .max_by(|a, b| {
+                let by_version = Version::parse(a).ok().cmp(&Version::parse(b).ok());
+                dependents(a).cmp(&dependents(b)).then(by_version)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Copies the tarball urls and integrity hashes of the imported lockfile into resolved
+    /// registry packages with the same name and version
+    pub fn apply(&self, packages: &mut BTreeMap<String, LockedPackage>) {
+        for package in packages.values_mut() {
+            if !matches!(
+                Specifier::parse(&package.resolved),
+                Specifier::RemoteTarball(_)
+            ) {
+                continue;
+            }
+
+            // the same version of a package is the same tarball under any name it is installed
+            // as, and pnpm only keys packages by their real name
+            let imported = self.packages.iter().find(|(_, imported)| {
+                imported.name == package.name && imported.version == package.version
+            });
+
+            let imported = match imported {
+                Some((_, imported)) => imported,
+                None => continue,
+            };
+
+            if matches!(
+                Specifier::parse(&imported.resolved),
+                Specifier::RemoteTarball(_)
+            ) {
+                package.resolved = imported.resolved.clone();
+            }
+
+            if !imported.integrity.is_empty() {
+                package.integrity = imported.integrity.clone();
+            }
+        }
+    }
+}
+
+/// The packages of a lockfile along with the versions locked for the root dependencies and the
+/// versions installed at the top of `node_modules`, if the lockfile records them
+type ImportedPackages = (
+    Vec<(String, LockedPackage)>,
+    BTreeMap<String, String>,
+    BTreeMap<String, String>,
+);
+
+type ImportParser =
+    fn(&str, &BTreeMap<String, String>) -> std::result::Result<ImportedPackages, String>;
+
+/// npm lists every installed package under `packages`, keyed by its path, such as
+/// `node_modules/a/node_modules/b` for a copy of `b` nested below `a`
+fn parse_package_lock(
+    contents: &str,
+    _root_dependencies: &BTreeMap<String, String>,
+) -> std::result::Result<ImportedPackages, String> {
+    let package_lock: Value = match serde_json::from_str(contents) {
+        Ok(package_lock) => package_lock,
+        Err(error) => return Err(error.to_string()),
+    };
+
+    if package_lock["lockfileVersion"].as_u64().unwrap_or(1) < 2 {
+        return Err("only lockfileVersion 2 and 3 are supported".to_string());
+    }
+
+    let entries = match package_lock["packages"].as_object() {
+        Some(entries) => entries,
+        None => return Err("missing `packages`".to_string()),
+    };
+
+    let mut packages = Vec::new();
+    let mut installed = BTreeMap::new();
+
+    for (path, entry) in entries {
+        let name = match path.rsplit_once("node_modules/") {
+            Some((_, name)) if !name.is_empty() => name,
+            _ => continue,
+        };
+
+        // workspaces and `link:` dependencies are symlinks to a directory of the project
+        if entry["link"].as_bool().unwrap_or(false) {
+            continue;
+        }
+
+        let version = match entry["version"].as_str() {
+            Some(version) => version,
+            None => continue,
+        };
+
+        // packages of workspaces are installed in the `node_modules` of the workspace
+        if let Some(install_path) = path.strip_prefix("node_modules/") {
+            installed.insert(install_path.to_string(), version.to_string());
+        }
+
+        packages.push((
+            name.to_string(),
+            LockedPackage {
+                name: entry["name"].as_str().unwrap_or(name).to_string(),
+                version: version.to_string(),
+                resolved: entry["resolved"].as_str().unwrap_or_default().to_string(),
+                integrity: entry["integrity"].as_str().unwrap_or_default().to_string(),
+                dependencies: string_map(&entry["dependencies"]),
+            },
+        ));
+    }
+
+    Ok((packages, BTreeMap::new(), installed))
+}
+
+/// yarn v1 lockfiles are blocks headed by the descriptors (`name@range`) they resolve, followed
+/// by indented `key value` fields:
+///
+/// ```text
+/// "lodash@^4.17.0", lodash@^4.17.21:
+///   version "4.17.21"
+///   resolved "https://registry.yarnpkg.com/lodash/-/lodash-4.17.21.tgz#679591c5..."
+///   integrity sha512-v2kDE...
+/// ```
+fn parse_yarn_lock(
+    contents: &str,
+    root_dependencies: &BTreeMap<String, String>,
+) -> std::result::Result<ImportedPackages, String> {
+    if contents.lines().any(|line| line.starts_with("__metadata:")) {
+        return Err("only yarn v1 lockfiles are supported".to_string());
+    }
+
+    let mut packages = Vec::new();
+    let mut root_versions = BTreeMap::new();
+
+    let mut descriptors: Vec<(String, String)> = Vec::new();
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    let mut dependencies: BTreeMap<String, String> = BTreeMap::new();
+    let mut in_dependencies = false;
+
+    // a trailing empty line flushes the last block
+    for line in contents.lines().chain(std::iter::once("")) {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        if line.trim().is_empty() || !line.starts_with(' ') {
+            if !descriptors.is_empty() {
+                let (block_packages, block_root_versions) =
+                    yarn_block(&descriptors, &fields, &dependencies, root_dependencies);
+                packages.extend(block_packages);
+                root_versions.extend(block_root_versions);
+            }
+
+            descriptors.clear();
+            fields.clear();
+            dependencies.clear();
+            in_dependencies = false;
+
+            if let Some(header) = line.trim_end().strip_suffix(':') {
+                descriptors = header
+                    .split(", ")
+                    .map(|descriptor| split_descriptor(unquote(descriptor.trim())))
+                    .collect();
+            }
+
+            continue;
+        }
+
+        let (key, value) = match line.trim().split_once(' ') {
+            Some((key, value)) => (unquote(key), unquote(value.trim())),
+            None => (unquote(line.trim().trim_end_matches(':')), ""),
+        };
+
+        if line.starts_with("    ") {
+            if in_dependencies {
+                dependencies.insert(key.to_string(), value.to_string());
+            }
+        } else if value.is_empty() {
+            in_dependencies = key == "dependencies" || key == "optionalDependencies";
+        } else {
+            in_dependencies = false;
+            fields.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    Ok((packages, root_versions, BTreeMap::new()))
+}
+
+fn yarn_block(
+    descriptors: &[(String, String)],
+    fields: &BTreeMap<String, String>,
+    dependencies: &BTreeMap<String, String>,
+    root_dependencies: &BTreeMap<String, String>,
+) -> (Vec<(String, LockedPackage)>, BTreeMap<String, String>) {
+    let mut packages = Vec::new();
+    let mut root_versions = BTreeMap::new();
+
+    let version = match fields.get("version") {
+        Some(version) => version,
+        None => return (packages, root_versions),
+    };
+
+    // the url ends with the sha1 of the tarball, which the integrity field already covers
+    let resolved = fields
+        .get("resolved")
+        .map(|resolved| resolved.split('#').next().unwrap_or_default().to_string())
+        .unwrap_or_default();
+
+    for (name, range) in descriptors {
+        let real_name = match range.strip_prefix("npm:") {
+            Some(aliased) => split_package_spec(aliased).0,
+            None => name,
+        };
+
+        if root_dependencies.get(name) == Some(range) {
+            root_versions.insert(name.clone(), version.clone());
+        }
+
+        if packages.iter().any(|(other, _)| other == name) {
+            continue;
+        }
+
+        packages.push((
+            name.clone(),
+            LockedPackage {
+                name: real_name.to_string(),
+                version: version.clone(),
+                resolved: resolved.clone(),
+                integrity: fields.get("integrity").cloned().unwrap_or_default(),
+                dependencies: dependencies.clone(),
+            },
+        ));
+    }
+
+    (packages, root_versions)
+}
+
+/// Splits a yarn descriptor at the `@` following the name, so that aliases such as
+/// `foo@npm:bar@^1.0.0` keep their range intact
+fn split_descriptor(descriptor: &str) -> (String, String) {
+    let start = usize::from(descriptor.starts_with('@'));
+
+    match descriptor[start..].find('@') {
+        Some(index) => (
+            descriptor[..start + index].to_string(),
+            descriptor[start + index + 1..].to_string(),
+        ),
+        None => (descriptor.to_string(), String::new()),
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim_matches('"')
+}
+
+/// pnpm keys `packages` by name and exact version, written as `/name/1.0.0` up to lockfile
+/// version 5, `/name@1.0.0` in version 6 and `name@1.0.0` from version 9 on, with peer
+/// dependencies appended as `_peer@1.0.0` or `(peer@1.0.0)`
+fn parse_pnpm_lock(
+    contents: &str,
+    _root_dependencies: &BTreeMap<String, String>,
+) -> std::result::Result<ImportedPackages, String> {
+    let pnpm_lock: Value = match serde_yaml::from_str(contents) {
+        Ok(pnpm_lock) => pnpm_lock,
+        Err(error) => return Err(error.to_string()),
+    };
+
+    let lockfile_version = match &pnpm_lock["lockfileVersion"] {
+        Value::String(version) => version.parse().unwrap_or_default(),
+        version => version.as_f64().unwrap_or_default(),
+    };
+
+    let mut packages = Vec::new();
+
+    if let Some(entries) = pnpm_lock["packages"].as_object() {
+        for (key, entry) in entries {
+            let key = key.trim_start_matches('/');
+
+            let split = if lockfile_version < 6.0 {
+                key.rsplit_once('/')
+            } else {
+                match split_package_spec(key.split('(').next().unwrap_or_default()) {
+                    (name, Some(version)) => Some((name, version)),
+                    _ => None,
+                }
+            };
+
+            let (name, version) = match split {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let version = match entry["version"].as_str() {
+                Some(version) => version.to_string(),
+                None => clean_pnpm_version(version),
+            };
+
+            if Version::parse(&version).is_err() {
+                continue;
+            }
+
+            packages.push((
+                entry["name"].as_str().unwrap_or(name).to_string(),
+                LockedPackage {
+                    name: entry["name"].as_str().unwrap_or(name).to_string(),
+                    version,
+                    resolved: entry["resolution"]["tarball"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    integrity: entry["resolution"]["integrity"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    dependencies: string_map(&entry["dependencies"]),
+                },
+            ));
+        }
+    }
+
+    // the root project is the `.` importer, or the lockfile itself before importers existed
+    let root = match pnpm_lock["importers"].get(".") {
+        Some(root) => root,
+        None => &pnpm_lock,
+    };
+
+    let mut root_versions = BTreeMap::new();
+
+    for field in ["dependencies", "devDependencies", "optionalDependencies"] {
+        if let Some(dependencies) = root[field].as_object() {
+            for (name, dependency) in dependencies {
+                let version = match dependency {
+                    Value::String(version) => version.as_str(),
+                    _ => dependency["version"].as_str().unwrap_or_default(),
+                };
+
+                let version = root_pnpm_version(version, lockfile_version);
+                if Version::parse(&version).is_ok() {
+                    root_versions.insert(name.clone(), version);
+                }
+            }
+        }
+    }
+
+    Ok((packages, root_versions, BTreeMap::new()))
+}
+
+/// The version locked for a root dependency. Aliases are locked as the package they install,
+/// written as `/string-width/4.2.3` up to lockfile version 5, `/string-width@4.2.3` in version 6
+/// and `string-width@4.2.3` from version 9 on
+fn root_pnpm_version(version: &str, lockfile_version: f64) -> String {
+    let version = clean_pnpm_version(version);
+    let version = version.trim_start_matches('/');
+
+    let version = if lockfile_version < 6.0 {
+        version.rsplit('/').next().unwrap_or_default()
+    } else {
+        split_package_spec(version).1.unwrap_or(version)
+    };
+
+    version.to_string()
+}
+
+fn clean_pnpm_version(version: &str) -> String {
+    version
+        .split(['(', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn string_map(value: &Value) -> BTreeMap<String, String> {
+    match value.as_object() {
+        Some(object) => object
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect(),
+        None => BTreeMap::new(),
+    }
+}
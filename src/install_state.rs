@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::lockfile::{parent_path, LockedPackage};
+use crate::resolver::ResolvedGraph;
+use crate::specifier::Specifier;
+
+/// The file in `node_modules` recording what the last install put there
+pub const INSTALL_STATE_NAME: &str = ".blaze-state.json";
+
+/// What the last install materialized in `node_modules`, so that the next one only adds,
+/// removes or replaces the packages that changed. Like npm's `node_modules/.package-lock.json`
+/// it is only a cache: when it is missing or unreadable, every package is linked again
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct InstallState {
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Workspaces symlinked into `node_modules`, mapped to their directories relative to the
+    /// project root
+    pub workspaces: BTreeMap<String, PathBuf>,
+}
+
+impl InstallState {
+    /// The state of a completed install of `graph`
+    pub fn from_graph(graph: &ResolvedGraph) -> InstallState {
+        InstallState {
+            packages: graph.packages.clone(),
+            workspaces: linked_workspaces(graph),
+        }
+    }
+
+    /// The state once `installed`, a part of `graph`, was installed over this one. The
+    /// packages of `graph` that were left alone are still as this state recorded them
+    pub fn updated(&self, graph: &ResolvedGraph, installed: &ResolvedGraph) -> InstallState {
+        let mut packages: BTreeMap<String, LockedPackage> = self
+            .packages
+            .iter()
+            .filter(|(name, _)| graph.packages.contains_key(*name))
+            .map(|(name, package)| (name.clone(), package.clone()))
+            .collect();
+        packages.extend(installed.packages.clone());
+
+        InstallState {
+            packages,
+            workspaces: linked_workspaces(installed),
+        }
+    }
+
+    pub fn read(node_modules: &Path) -> InstallState {
+        fs::read_to_string(node_modules.join(INSTALL_STATE_NAME))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the state next to the packages. The state is written to a temporary file that
+    /// replaces the old one, so an interrupted install never leaves a truncated state behind
+    pub fn write(&self, node_modules: &Path) -> Result<()> {
+        let contents = match serde_json::to_string_pretty(self) {
+            Ok(contents) => contents,
+            Err(error) => {
+                return Err(Error::Lockfile {
+                    path: INSTALL_STATE_NAME.to_string(),
+                    reason: error.to_string(),
+                })
+            }
+        };
+
+        fs::create_dir_all(node_modules).map_err(Error::io(node_modules))?;
+
+        let path = node_modules.join(INSTALL_STATE_NAME);
+        let temporary = node_modules.join(format!("{}.tmp", INSTALL_STATE_NAME));
+
+        fs::write(&temporary, contents).map_err(Error::io(&temporary))?;
+        fs::rename(&temporary, &path).map_err(Error::io(&path))
+    }
+
+    /// The part of `graph` that still has to be fetched and linked: the packages and
+    /// workspaces that are new, differ from what was installed, or went missing from
+    /// `node_modules` since. Linking a package again replaces its `node_modules`, so the
+    /// packages nested in it are linked again too
+    pub fn pending(&self, graph: &ResolvedGraph, node_modules: &Path) -> ResolvedGraph {
+        let is_present = |name: &str| node_modules.join(name).symlink_metadata().is_ok();
+
+        let mut packages: BTreeMap<String, LockedPackage> = BTreeMap::new();
+
+        // packages come before the ones nested in them, which share their path as a prefix
+        for (path, package) in &graph.packages {
+            let is_linked = self
+                .packages
+                .get(path)
+                .map_or(false, |installed| is_unchanged(installed, package))
+                && is_present(path);
+            let parent_is_pending =
+                parent_path(path).map_or(false, |parent| packages.contains_key(parent));
+
+            if !is_linked || parent_is_pending {
+                packages.insert(path.clone(), package.clone());
+            }
+        }
+
+        let sources = graph
+            .sources
+            .iter()
+            .filter(|(name, _)| packages.contains_key(*name))
+            .map(|(name, source)| (name.clone(), source.clone()))
+            .collect();
+
+        ResolvedGraph {
+            packages,
+            workspaces: linked_workspaces(graph)
+                .into_iter()
+                .filter(|(name, path)| {
+                    !(self.workspaces.get(name) == Some(path) && is_present(name))
+                })
+                .collect(),
+            sources,
+        }
+    }
+
+    /// The packages and workspaces that were installed but are no longer part of `graph`
+    pub fn stale(&self, graph: &ResolvedGraph) -> Vec<String> {
+        self.packages
+            .keys()
+            .chain(self.workspaces.keys())
+            .filter(|name| {
+                !graph.packages.contains_key(*name) && !graph.workspaces.contains_key(*name)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// The workspaces of `graph` that are symlinked, which excludes the ones a package of the same
+/// name takes precedence over
+fn linked_workspaces(graph: &ResolvedGraph) -> BTreeMap<String, PathBuf> {
+    graph
+        .workspaces
+        .iter()
+        .filter(|(name, _)| !graph.packages.contains_key(*name))
+        .map(|(name, path)| (name.clone(), path.clone()))
+        .collect()
+}
+
+/// Whether an installed package can be kept as it is. Packages copied from a directory have no
+/// integrity to compare, and their contents may change without their version changing, so they
+/// are always copied again
+fn is_unchanged(installed: &LockedPackage, package: &LockedPackage) -> bool {
+    installed == package
+        && (!package.integrity.is_empty()
+            || matches!(Specifier::parse(&package.resolved), Specifier::Link(_)))
+}
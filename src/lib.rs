@@ -1,2 +1,29 @@
+pub mod concurrency;
+pub mod dedupe;
 pub mod dependencies;
+pub mod error;
+pub mod exec;
+pub mod export;
+pub mod fetcher;
+pub mod import;
+pub mod install_state;
+pub mod linker;
+pub mod list;
+pub mod lockfile;
+pub mod npmrc;
+pub mod outdated;
+pub mod overrides;
+pub mod project;
+pub mod prune;
+pub mod registry;
+pub mod reporter;
+pub mod resolver;
+pub mod scripts;
+pub mod sources;
+pub mod specifier;
+pub mod store;
 pub mod utils;
+pub mod version_range;
+pub mod workspaces;
+
+pub use error::{Error, Result};
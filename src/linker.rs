@@ -0,0 +1,522 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::concurrency::Concurrency;
+use crate::error::{Error, PackageFailure, Result, Stage};
+use crate::fetcher::FetchedPackages;
+use crate::lockfile::{installed_name, parent_path, NESTED_SEPARATOR};
+use crate::reporter::{Event, Reporter, SilentReporter};
+use crate::resolver::ResolvedGraph;
+use crate::sources::copy_package;
+use crate::specifier::Specifier;
+use crate::utils::joined;
+
+/// The directory in `node_modules` packages are assembled in before they are renamed into
+/// place, so that `node_modules` never holds a half-copied package
+const STAGING_DIRECTORY: &str = ".blaze-staging";
+
+/// Tells apart the staging entries of packages linked at the same time
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Materializes a `ResolvedGraph` in `node_modules`: fetched packages are copied out of the
+/// store or their source directory, `link:` dependencies and workspaces are symlinked, and
+/// binaries are linked into `node_modules/.bin`
+#[derive(Clone)]
+pub struct Linker {
+    node_modules: PathBuf,
+    reporter: Arc<dyn Reporter>,
+    concurrency: Concurrency,
+}
+
+impl Linker {
+    pub fn new(node_modules: &Path) -> Linker {
+        Linker {
+            node_modules: node_modules.to_path_buf(),
+            reporter: Arc::new(SilentReporter),
+            concurrency: Concurrency::default(),
+        }
+    }
+
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Linker {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Every package copied or symlinked holds a file system slot
+    pub fn with_concurrency(mut self, concurrency: Concurrency) -> Linker {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Links every package of `graph` into `node_modules`. Packages missing from `fetched` are
+    /// skipped. The other packages are still linked when one fails, and every failure is
+    /// returned together as an `Error::Install`
+    pub async fn link(&self, graph: &ResolvedGraph, fetched: &FetchedPackages) -> Result<()> {
+        // whatever an interrupted install left in the staging directory is incomplete
+        let staging = self.node_modules.join(STAGING_DIRECTORY);
+        remove_existing(&staging)?;
+        fs::create_dir_all(&staging).map_err(Error::io(&staging))?;
+
+        let workspaces: Vec<(String, PathBuf)> = graph
+            .workspaces
+            .iter()
+            .filter(|(name, _)| !graph.packages.contains_key(*name))
+            .map(|(name, path)| (name.clone(), path.clone()))
+            .collect();
+
+        self.reporter.report(Event::LinkStarted {
+            packages: graph.packages.len() + workspaces.len(),
+        });
+
+        // linking a package replaces its directory, so the packages nested in it are only
+        // linked once it is in place, one level of nesting at a time
+        let mut depths: BTreeMap<usize, Vec<(&String, LinkSource)>> = BTreeMap::new();
+
+        for (path, package) in &graph.packages {
+            let source = match Specifier::parse(&package.resolved) {
+                Specifier::Link(directory) => LinkSource::Symlink(directory),
+                _ => match fetched.get(path) {
+                    Some(directory) => LinkSource::Copy(directory.clone()),
+                    None => continue,
+                },
+            };
+
+            let depth = path.matches(NESTED_SEPARATOR).count();
+            depths.entry(depth).or_default().push((path, source));
+        }
+        // workspaces are linked at the top along with the packages there
+        depths.entry(0).or_default();
+
+        let mut failures = Vec::new();
+
+        for (depth, packages) in depths {
+            let mut handles = Vec::new();
+
+            for (path, source) in packages {
+                // scoped packages are linked into their scope directory
+                if let Some(parent) = self.node_modules.join(path).parent() {
+                    fs::create_dir_all(parent).map_err(Error::io(parent))?;
+                }
+
+                handles.push(self.spawn_link(path.clone(), source));
+            }
+
+            if depth == 0 {
+                for (name, path) in &workspaces {
+                    handles.push(self.spawn_link(name.clone(), LinkSource::Symlink(path.clone())));
+                }
+            }
+
+            self.collect_links(handles, &mut failures).await;
+        }
+
+        let _ = fs::remove_dir_all(&staging);
+
+        self.reporter.report(Event::LinkFinished);
+
+        if !failures.is_empty() {
+            return Err(Error::Install { failures });
+        }
+
+        Ok(())
+    }
+
+    /// Reports the links of `handles` as they complete, adding their failures to `failures`
+    async fn collect_links(
+        &self,
+        handles: Vec<tokio::task::JoinHandle<(String, Result<()>)>>,
+        failures: &mut Vec<PackageFailure>,
+    ) {
+        for handle in handles {
+            let (name, result) = joined(handle.await);
+
+            match result {
+                Ok(()) => self.reporter.report(Event::Linked { name }),
+                Err(error) => failures.push(PackageFailure {
+                    package: name,
+                    stage: Stage::Link,
+                    error,
+                }),
+            }
+        }
+    }
+
+    /// Removes packages, given by their install paths, from `node_modules`, along with the
+    /// binaries they linked into the `.bin` directory next to them. Packages nested in a removed
+    /// package go away with it
+    pub fn unlink(&self, paths: &[String]) -> Result<()> {
+        for path in paths {
+            let directory = self.node_modules.join(path);
+            if directory.symlink_metadata().is_err() {
+                continue;
+            }
+
+            let (bin_root, name) = binary_root(&self.node_modules, path);
+            unlink_package_binaries(&bin_root, name)?;
+            remove_existing(&directory)?;
+
+            // scope directories and nested `node_modules` go away with their last package
+            remove_empty_parents(&self.node_modules, &directory);
+
+            self.reporter.report(Event::Removed { name: path.clone() });
+        }
+
+        Ok(())
+    }
+
+    fn spawn_link(
+        &self,
+        name: String,
+        source: LinkSource,
+    ) -> tokio::task::JoinHandle<(String, Result<()>)> {
+        let node_modules = self.node_modules.clone();
+        let concurrency = self.concurrency.clone();
+        let reporter = self.reporter.clone();
+
+        tokio::spawn(async move {
+            let permit = concurrency.io().await;
+
+            joined(
+                tokio::task::spawn_blocking(move || {
+                    let _permit = permit;
+                    let result = match source {
+                        LinkSource::Symlink(path) => {
+                            link_directory(&node_modules, &name, &path, reporter.as_ref())
+                        }
+                        LinkSource::Copy(directory) => {
+                            copy_into(&node_modules, &name, &directory, reporter.as_ref())
+                        }
+                    };
+
+                    (name, result)
+                })
+                .await,
+            )
+        })
+    }
+}
+
+enum LinkSource {
+    /// A directory relative to the project root, symlinked into `node_modules`
+    Symlink(PathBuf),
+    /// A directory in the store or a staged source, copied into `node_modules`
+    Copy(PathBuf),
+}
+
+fn copy_into(
+    node_modules: &Path,
+    package_name: &str,
+    directory: &Path,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let staging = staging_path(node_modules);
+
+    let result = copy_package(directory, &staging)
+        .and_then(|()| replace(&staging, &node_modules.join(package_name)));
+    if result.is_err() {
+        let _ = remove_existing(&staging);
+    }
+    result?;
+
+    let (bin_root, name) = binary_root(node_modules, package_name);
+    link_package_binaries(&bin_root, name, reporter)
+}
+
+/// The `node_modules` directory a package installed at `path` sits in, whose `.bin` gets its
+/// binaries, along with the name of the package in it
+fn binary_root<'a>(node_modules: &Path, path: &'a str) -> (PathBuf, &'a str) {
+    match parent_path(path) {
+        Some(parent) => (
+            node_modules.join(parent).join("node_modules"),
+            installed_name(path),
+        ),
+        None => (node_modules.to_path_buf(), path),
+    }
+}
+
+/// A fresh path in the staging directory of `node_modules`
+fn staging_path(node_modules: &Path) -> PathBuf {
+    node_modules.join(STAGING_DIRECTORY).join(format!(
+        "{}-{}",
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+/// Renames the complete package at `staging` to `destination`. A package already there is
+/// moved aside first and only deleted once the new one is in place, so `destination` always
+/// holds either the old package or the new one, and is at worst missing
+fn replace(staging: &Path, destination: &Path) -> Result<()> {
+    let aside = staging.with_extension("old");
+
+    if destination.symlink_metadata().is_ok() {
+        fs::rename(destination, &aside).map_err(Error::io(destination))?;
+    }
+
+    if let Err(error) = fs::rename(staging, destination) {
+        let _ = fs::rename(&aside, destination);
+        return Err(Error::io(destination)(error));
+    }
+
+    remove_existing(&aside)
+}
+
+/// Removes the scope directories and nested `node_modules` left without packages after
+/// removing `path`, up to `node_modules` itself
+pub(crate) fn remove_empty_parents(node_modules: &Path, path: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent == node_modules || !parent.starts_with(node_modules) {
+            break;
+        }
+
+        let is_empty = match fs::read_dir(parent) {
+            Ok(entries) => entries.flatten().all(|entry| entry.file_name() == ".bin"),
+            Err(_) => false,
+        };
+
+        if !is_empty || fs::remove_dir_all(parent).is_err() {
+            break;
+        }
+    }
+}
+
+pub(crate) fn remove_existing(path: &Path) -> Result<()> {
+    let metadata = match path.symlink_metadata() {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+
+    let result = if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+
+    result.map_err(Error::io(path))
+}
+
+/// Symlinks a local package, such as a workspace, into `node_modules` so it can be required like
+/// any other package. `path` is relative to the project root
+fn link_directory(
+    node_modules: &Path,
+    package_name: &str,
+    path: &Path,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let link = node_modules.join(package_name);
+
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent).map_err(Error::io(parent))?;
+    }
+
+    // the link lives in `node_modules` (or `node_modules/@scope`), so climb back to the root
+    let mut target = PathBuf::new();
+    for _ in 0..package_name.split('/').count() {
+        target.push("..");
+    }
+    target.push(path);
+
+    // the link is created in the staging directory, but its target is relative to `link`
+    let staging = staging_path(node_modules);
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&target, &staging).map_err(Error::io(&staging))?;
+
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_dir(&target, &staging).map_err(Error::io(&staging))?;
+
+    if let Err(error) = replace(&staging, &link) {
+        let _ = remove_existing(&staging);
+        return Err(error);
+    }
+
+    let (bin_root, name) = binary_root(node_modules, package_name);
+    link_package_binaries(&bin_root, name, reporter)
+}
+
+/// Links the executables declared in the `bin` field of a package into `node_modules/.bin`.
+/// Binaries named like a path or pointing out of the package are skipped with a warning, since
+/// linking them would write outside of `node_modules/.bin` or expose files of other packages
+pub fn link_package_binaries(
+    node_modules: &Path,
+    package_name: &str,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let package_directory = node_modules.join(package_name);
+    let package_json_path = package_directory.join("package.json");
+    let package_json: Value = match fs::read_to_string(package_json_path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        Err(_) => return Ok(()),
+    };
+
+    let binaries = declared_binaries(package_name, &package_json);
+    if binaries.is_empty() {
+        return Ok(());
+    }
+
+    let bin_directory = node_modules.join(".bin");
+    fs::create_dir_all(&bin_directory).map_err(Error::io(&bin_directory))?;
+
+    for (bin_name, bin_path) in binaries {
+        if !is_valid_bin_name(&bin_name) {
+            reporter.report(Event::Warning {
+                message: format!(
+                    "{}: skipping the binary `{}`, its name is not a file name",
+                    package_name, bin_name
+                ),
+            });
+            continue;
+        }
+
+        if !is_inside(&package_directory, Path::new(&bin_path)) {
+            reporter.report(Event::Warning {
+                message: format!(
+                    "{}: skipping the binary `{}`, `{}` is outside of the package",
+                    package_name, bin_name, bin_path
+                ),
+            });
+            continue;
+        }
+
+        let target = Path::new("..").join(package_name).join(&bin_path);
+        link_binary(&bin_directory, &bin_name, &target)
+            .map_err(Error::io(bin_directory.join(&bin_name)))?;
+    }
+
+    Ok(())
+}
+
+/// Removes the binaries of a package from `node_modules/.bin`, leaving alone binaries of the
+/// same name that another package linked
+fn unlink_package_binaries(node_modules: &Path, package_name: &str) -> Result<()> {
+    let package_json_path = node_modules.join(package_name).join("package.json");
+    let package_json: Value = match fs::read_to_string(package_json_path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        Err(_) => return Ok(()),
+    };
+
+    let bin_directory = node_modules.join(".bin");
+
+    for (bin_name, bin_path) in package_binaries(package_name, &package_json) {
+        let target = Path::new("..").join(package_name).join(&bin_path);
+        unlink_binary(&bin_directory, &bin_name, &target)
+            .map_err(Error::io(bin_directory.join(&bin_name)))?;
+    }
+
+    Ok(())
+}
+
+/// Reads the `bin` field of a `package.json`, which is either a single path named after the
+/// package or a map of binary names to paths. Names that are not a plain file name are left out
+pub fn package_binaries(package_name: &str, package_json: &Value) -> BTreeMap<String, String> {
+    declared_binaries(package_name, package_json)
+        .into_iter()
+        .filter(|(bin_name, _)| is_valid_bin_name(bin_name))
+        .collect()
+}
+
+fn declared_binaries(package_name: &str, package_json: &Value) -> BTreeMap<String, String> {
+    let mut binaries = BTreeMap::new();
+
+    match &package_json["bin"] {
+        Value::String(path) => {
+            let bin_name = package_name.rsplit('/').next().unwrap_or(package_name);
+            binaries.insert(bin_name.to_string(), path.clone());
+        }
+        Value::Object(entries) => {
+            for (bin_name, path) in entries {
+                if let Some(path) = path.as_str() {
+                    binaries.insert(bin_name.clone(), path.to_string());
+                }
+            }
+        }
+        _ => (),
+    }
+
+    binaries
+}
+
+/// Whether `bin_name` can be linked as `node_modules/.bin/<bin_name>` without leaving it
+fn is_valid_bin_name(bin_name: &str) -> bool {
+    !bin_name.is_empty() && bin_name != "." && bin_name != ".." && !bin_name.contains(['/', '\\'])
+}
+
+/// Whether `path`, relative to `directory`, stays inside of it. Paths that exist are
+/// canonicalized so symlinks cannot lead out; the others, such as binaries a build script has
+/// yet to create, only must not climb out through `..`
+fn is_inside(directory: &Path, path: &Path) -> bool {
+    let mut depth = 0usize;
+
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => (),
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+
+    match (
+        directory.canonicalize(),
+        directory.join(path).canonicalize(),
+    ) {
+        (Ok(directory), Ok(target)) => target.starts_with(directory),
+        _ => true,
+    }
+}
+
+#[cfg(unix)]
+fn link_binary(bin_directory: &Path, bin_name: &str, target: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let link = bin_directory.join(bin_name);
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(&link)?;
+    }
+
+    std::os::unix::fs::symlink(target, &link)?;
+
+    let target = bin_directory.join(target);
+    if let Ok(metadata) = fs::metadata(&target) {
+        let mut permissions = metadata.permissions();
+        permissions.set_mode(permissions.mode() | 0o755);
+        fs::set_permissions(&target, permissions)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn link_binary(bin_directory: &Path, bin_name: &str, target: &Path) -> std::io::Result<()> {
+    let shim = format!(
+        "@node \"%~dp0\\{}\" %*\r\n",
+        target.display().to_string().replace('/', "\\")
+    );
+
+    fs::write(bin_directory.join(format!("{}.cmd", bin_name)), shim)
+}
+
+#[cfg(unix)]
+fn unlink_binary(bin_directory: &Path, bin_name: &str, target: &Path) -> std::io::Result<()> {
+    let link = bin_directory.join(bin_name);
+
+    match fs::read_link(&link) {
+        Ok(link_target) if link_target == target => fs::remove_file(&link),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn unlink_binary(bin_directory: &Path, bin_name: &str, target: &Path) -> std::io::Result<()> {
+    let shim_path = bin_directory.join(format!("{}.cmd", bin_name));
+    let target = target.display().to_string().replace('/', "\\");
+
+    match fs::read_to_string(&shim_path) {
+        Ok(shim) if shim.contains(&target) => fs::remove_file(&shim_path),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,284 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::dependencies::Dependencies;
+use crate::lockfile::{parent_path, Lockfile, NESTED_SEPARATOR};
+use crate::specifier::Specifier;
+use crate::utils::{installed_package_names, read_installed_package_json, read_package_json};
+use crate::version_range::VersionRange;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DependencyNode {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The range the parent asked for, empty for extraneous packages and workspaces
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub range: String,
+    /// The package is declared but not present in `node_modules`
+    #[serde(skip_serializing_if = "is_false")]
+    pub missing: bool,
+    /// The installed version does not satisfy `range`
+    #[serde(skip_serializing_if = "is_false")]
+    pub invalid: bool,
+    /// The package is in `node_modules` but nothing depends on it
+    #[serde(skip_serializing_if = "is_false")]
+    pub extraneous: bool,
+    /// The package was already expanded elsewhere in the tree
+    #[serde(skip_serializing_if = "is_false")]
+    pub deduped: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, DependencyNode>,
+}
+
+impl DependencyNode {
+    pub fn has_problems(&self) -> bool {
+        self.missing
+            || self.invalid
+            || self.extraneous
+            || self.dependencies.values().any(|node| node.has_problems())
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DependencyTree {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub dependencies: BTreeMap<String, DependencyNode>,
+}
+
+impl DependencyTree {
+    pub fn has_problems(&self) -> bool {
+        self.dependencies.values().any(|node| node.has_problems())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOptions {
+    /// How many levels below the root dependencies to show, `None` shows everything
+    pub depth: Option<usize>,
+    /// Only show `dependencies`
+    pub prod: bool,
+    /// Only show `devDependencies`
+    pub dev: bool,
+}
+
+/// Builds the tree of installed dependencies from `node_modules`, falling back to the edges
+/// recorded in `blaze.lock` for packages whose `package.json` cannot be read
+pub fn build_dependency_tree(dependencies: &Dependencies, options: ListOptions) -> DependencyTree {
+    let node_modules = dependencies.root.join("node_modules");
+
+    let package_json: Value = read_package_json(&dependencies.root)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+
+    let lockfile = Lockfile::read(&dependencies.root).unwrap_or_default();
+
+    let mut tree = DependencyTree {
+        name: package_json["name"].as_str().map(String::from),
+        version: package_json["version"].as_str().map(String::from),
+        dependencies: BTreeMap::new(),
+    };
+
+    let show_prod = options.prod || !options.dev;
+    let show_dev = options.dev || !options.prod;
+
+    let mut root_dependencies = BTreeMap::new();
+    if show_dev {
+        root_dependencies.extend(dependencies.dev_dependencies.clone());
+    }
+    if show_prod {
+        root_dependencies.extend(dependencies.dependencies.clone());
+    }
+
+    let mut expanded = BTreeSet::new();
+
+    for (name, range) in root_dependencies {
+        let node = build_node(
+            &node_modules,
+            &name,
+            &name,
+            &range,
+            options.depth,
+            &lockfile,
+            &mut expanded,
+        );
+        tree.dependencies.insert(name, node);
+    }
+
+    // workspaces are linked into `node_modules` without the project depending on them
+    for name in dependencies.workspaces.keys() {
+        if tree.dependencies.contains_key(name) {
+            continue;
+        }
+
+        let node = build_node(
+            &node_modules,
+            name,
+            name,
+            "",
+            options.depth,
+            &lockfile,
+            &mut expanded,
+        );
+        tree.dependencies.insert(name.clone(), node);
+    }
+
+    let mut reachable = BTreeSet::new();
+    for name in dependencies
+        .root_dependencies()
+        .keys()
+        .chain(dependencies.workspaces.keys())
+    {
+        collect_reachable(&node_modules, name, &lockfile, &mut reachable);
+    }
+
+    for name in installed_package_names(&node_modules) {
+        if reachable.contains(&name) {
+            continue;
+        }
+
+        let version = read_installed_package_json(&node_modules, &name)
+            .and_then(|package_json| package_json["version"].as_str().map(String::from));
+
+        tree.dependencies.insert(
+            name.clone(),
+            DependencyNode {
+                name,
+                version,
+                extraneous: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    tree
+}
+
+/// Builds the node of the package `name` that is installed at `path`, relative to
+/// `node_modules`
+fn build_node(
+    node_modules: &Path,
+    path: &str,
+    name: &str,
+    range: &str,
+    depth: Option<usize>,
+    lockfile: &Lockfile,
+    expanded: &mut BTreeSet<String>,
+) -> DependencyNode {
+    let mut node = DependencyNode {
+        name: name.to_string(),
+        range: range.to_string(),
+        ..Default::default()
+    };
+
+    let package_json = match read_installed_package_json(node_modules, path) {
+        Some(package_json) => package_json,
+        None => {
+            node.missing = true;
+            return node;
+        }
+    };
+
+    node.version = package_json["version"].as_str().map(String::from);
+
+    let range = Specifier::parse(range)
+        .registry_package(name)
+        .and_then(|(_, range)| VersionRange::parse(&range).ok());
+
+    if let (Some(version), Some(range)) = (&node.version, range) {
+        node.invalid = match semver::Version::parse(version) {
+            Ok(version) => !range.matches(&version),
+            Err(_) => true,
+        };
+    }
+
+    let children = package_dependencies(path, Some(&package_json), lockfile);
+
+    if children.is_empty() || depth == Some(0) {
+        return node;
+    }
+
+    if !expanded.insert(path.to_string()) {
+        node.deduped = true;
+        return node;
+    }
+
+    for (child_name, child_range) in children {
+        let child = build_node(
+            node_modules,
+            &find_on_disk(node_modules, path, &child_name),
+            &child_name,
+            &child_range,
+            depth.map(|depth| depth - 1),
+            lockfile,
+            expanded,
+        );
+        node.dependencies.insert(child_name, child);
+    }
+
+    node
+}
+
+fn collect_reachable(
+    node_modules: &Path,
+    path: &str,
+    lockfile: &Lockfile,
+    reachable: &mut BTreeSet<String>,
+) {
+    if !reachable.insert(path.to_string()) {
+        return;
+    }
+
+    let package_json = read_installed_package_json(node_modules, path);
+
+    for child_name in package_dependencies(path, package_json.as_ref(), lockfile).keys() {
+        let child_path = find_on_disk(node_modules, path, child_name);
+        collect_reachable(node_modules, &child_path, lockfile, reachable);
+    }
+}
+
+/// The path, relative to `node_modules`, of the copy of `name` that the package installed at
+/// `from` requires, which is the one at the top when there is none nested on the way
+fn find_on_disk(node_modules: &Path, from: &str, name: &str) -> String {
+    let mut parent = Some(from);
+
+    while let Some(directory) = parent {
+        let path = format!("{}{}{}", directory, NESTED_SEPARATOR, name);
+        if node_modules.join(&path).symlink_metadata().is_ok() {
+            return path;
+        }
+
+        parent = parent_path(directory);
+    }
+
+    name.to_string()
+}
+
+fn package_dependencies(
+    path: &str,
+    package_json: Option<&Value>,
+    lockfile: &Lockfile,
+) -> BTreeMap<String, String> {
+    if let Some(dependencies) = package_json.and_then(|json| json["dependencies"].as_object()) {
+        return dependencies
+            .iter()
+            .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+            .collect();
+    }
+
+    match lockfile.packages.get(path) {
+        Some(package) if package_json.is_none() => package.dependencies.clone(),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::utils::validate_package_name;
+
+pub const LOCKFILE_NAME: &str = "blaze.lock";
+
+/// What separates a package from the packages nested in its `node_modules` in an install path
+pub const NESTED_SEPARATOR: &str = "/node_modules/";
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct Lockfile {
+    /// The dependencies declared in `package.json` when the lockfile was written
+    pub dependencies: BTreeMap<String, String>,
+    /// The `devDependencies` declared in `package.json` when the lockfile was written
+    pub dev_dependencies: BTreeMap<String, String>,
+    /// The workspace packages linked into `node_modules`, mapped to their directories
+    pub workspaces: BTreeMap<String, String>,
+    /// The overrides and resolutions in effect when the lockfile was written
+    pub overrides: BTreeMap<String, String>,
+    /// Every resolved package keyed by the path it is installed at, relative to `node_modules`
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// The real name of the package, which differs from its key for `npm:` aliases
+    pub name: String,
+    pub version: String,
+    /// Where the package was fetched from: a tarball url, or a `file:`, `link:` or `git+`
+    /// specifier pinned to an exact commit
+    pub resolved: String,
+    /// The subresource integrity of the tarball, e.g. `sha512-...`
+    pub integrity: String,
+    /// The dependencies declared by this package, mapped to their version ranges
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /// Reads the `blaze.lock` of the project in `root`, returning `None` when it is missing,
+    /// was written by an incompatible version of blaze or locks a package under an invalid name,
+    /// so that the dependencies are resolved again
+    pub fn read(root: &Path) -> Option<Lockfile> {
+        let path = root.join(LOCKFILE_NAME);
+        if !path.exists() {
+            return None;
+        }
+
+        let buffer = fs::read(path).ok()?;
+
+        let lockfile: Lockfile = bincode::deserialize(&buffer).ok()?;
+
+        let has_valid_names = lockfile.packages.keys().all(|path| {
+            path.split(NESTED_SEPARATOR)
+                .all(|name| validate_package_name(name).is_ok())
+        });
+
+        has_valid_names.then_some(lockfile)
+    }
+
+    pub fn versions(&self) -> BTreeMap<String, String> {
+        self.packages
+            .iter()
+            .map(|(name, package)| (name.clone(), package.version.clone()))
+            .collect()
+    }
+
+    /// Finds every chain of dependencies leading from the root `package.json` to `package_name`,
+    /// optionally restricted to a specific version. Each path starts with a root dependency and
+    /// ends with the package itself, as `(name, version)` pairs. Dependencies are followed to
+    /// the copy the dependent finds in `node_modules`, as Node.js would
+    pub fn dependency_paths(
+        &self,
+        package_name: &str,
+        version: Option<&str>,
+    ) -> Vec<Vec<(String, String)>> {
+        let mut paths = Vec::new();
+        let mut current_path = Vec::new();
+
+        let root_names: BTreeSet<&String> = self
+            .dependencies
+            .keys()
+            .chain(self.dev_dependencies.keys())
+            .collect();
+
+        for name in root_names {
+            self.collect_dependency_paths(
+                name,
+                package_name,
+                version,
+                &mut current_path,
+                &mut paths,
+            );
+        }
+
+        paths
+    }
+
+    fn collect_dependency_paths(
+        &self,
+        installed_at: &str,
+        package_name: &str,
+        version: Option<&str>,
+        current_path: &mut Vec<(String, String)>,
+        paths: &mut Vec<Vec<(String, String)>>,
+    ) {
+        let package = match self.packages.get(installed_at) {
+            Some(package) => package,
+            None => return,
+        };
+        let name = installed_name(installed_at);
+
+        // dependency cycles would otherwise send us around in circles
+        if current_path.iter().any(|(visited, visited_version)| {
+            visited == name && *visited_version == package.version
+        }) {
+            return;
+        }
+
+        current_path.push((name.to_string(), package.version.clone()));
+
+        let matches_name = name == package_name || package.name == package_name;
+
+        if matches_name && version.map_or(true, |version| version == package.version) {
+            paths.push(current_path.clone());
+        } else {
+            for dependency_name in package.dependencies.keys() {
+                let dependency_path =
+                    match find_installed(&self.packages, installed_at, dependency_name) {
+                        Some(dependency_path) => dependency_path,
+                        None => continue,
+                    };
+
+                self.collect_dependency_paths(
+                    &dependency_path,
+                    package_name,
+                    version,
+                    current_path,
+                    paths,
+                );
+            }
+        }
+
+        current_path.pop();
+    }
+
+    pub fn write(&self, root: &Path) -> Result<()> {
+        let buffer = match bincode::serialize(self) {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                return Err(Error::Lockfile {
+                    path: LOCKFILE_NAME.to_string(),
+                    reason: error.to_string(),
+                })
+            }
+        };
+
+        let path = root.join(LOCKFILE_NAME);
+        let mut lock_file = File::create(&path).map_err(Error::io(&path))?;
+
+        lock_file.write_all(&buffer).map_err(Error::io(&path))
+    }
+}
+
+/// The name a package installed at `path` is required by, the last part of its install path
+pub fn installed_name(path: &str) -> &str {
+    path.rsplit_once(NESTED_SEPARATOR)
+        .map_or(path, |(_, name)| name)
+}
+
+/// The install path of the package that `path` is nested in, `None` at the top of `node_modules`
+pub fn parent_path(path: &str) -> Option<&str> {
+    path.rsplit_once(NESTED_SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// The install path of `name` as the package installed at `from` finds it, looking into the
+/// `node_modules` of `from` and of every package it is nested in before the top of
+/// `node_modules`, the way Node.js resolves `require`. An empty `from` is the project itself
+pub fn find_installed<T>(packages: &BTreeMap<String, T>, from: &str, name: &str) -> Option<String> {
+    let mut parent = Some(from).filter(|from| !from.is_empty());
+
+    while let Some(directory) = parent {
+        let path = format!("{}{}{}", directory, NESTED_SEPARATOR, name);
+        if packages.contains_key(&path) {
+            return Some(path);
+        }
+
+        parent = parent_path(directory);
+    }
+
+    packages.contains_key(name).then(|| name.to_string())
+}
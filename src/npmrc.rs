@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const NPMRC_NAME: &str = ".npmrc";
+
+/// The settings of the `.npmrc` files blaze understands. Settings in the project `.npmrc`
+/// take precedence over the ones in the `.npmrc` of the user
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Npmrc {
+    settings: BTreeMap<String, String>,
+}
+
+impl Npmrc {
+    /// Reads the `.npmrc` in `root` and the one in the home directory. Missing or unreadable
+    /// files are treated as empty
+    pub fn load(root: &Path) -> Npmrc {
+        let mut npmrc = Npmrc::default();
+
+        if let Some(home) = dirs::home_dir() {
+            npmrc.read(&home.join(NPMRC_NAME));
+        }
+        npmrc.read(&root.join(NPMRC_NAME));
+
+        npmrc
+    }
+
+    /// Parses `key = value` lines, skipping blank lines and `#` or `;` comments
+    pub fn parse(contents: &str) -> Npmrc {
+        let mut npmrc = Npmrc::default();
+        npmrc.extend(contents);
+        npmrc
+    }
+
+    fn read(&mut self, path: &Path) {
+        if let Ok(contents) = fs::read_to_string(path) {
+            self.extend(&contents);
+        }
+    }
+
+    fn extend(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim().trim_matches('"');
+                self.settings
+                    .insert(key.trim().to_string(), value.to_string());
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(String::as_str)
+    }
+
+    /// The maximum number of connections to the registry at once
+    pub fn maxsockets(&self) -> Option<usize> {
+        self.get("maxsockets")?
+            .parse()
+            .ok()
+            .filter(|&limit| limit > 0)
+    }
+}
@@ -0,0 +1,96 @@
+use serde::Serialize;
+
+use crate::dependencies::{max_satisfying_version, Dependencies};
+use crate::error::{Error, Result};
+use crate::lockfile::Lockfile;
+use crate::specifier::Specifier;
+use crate::utils::{joined, read_installed_version};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OutdatedDependency {
+    #[serde(skip)]
+    pub name: String,
+    /// The installed version, or the locked one when `node_modules` is missing it
+    pub current: Option<String>,
+    /// The highest version satisfying the range in `package.json`
+    pub wanted: String,
+    /// The version tagged as `latest` on the registry
+    pub latest: String,
+    #[serde(skip)]
+    pub range: String,
+}
+
+impl OutdatedDependency {
+    pub fn is_outdated(&self) -> bool {
+        self.current.as_deref() != Some(&self.wanted)
+            || self.current.as_deref() != Some(&self.latest)
+    }
+}
+
+/// Compares every dependency in `package.json` against the registry, returning only the ones
+/// that are not on their latest version. The packuments are fetched within the network limit of
+/// `dependencies.concurrency`
+pub async fn find_outdated_dependencies(
+    dependencies: &Dependencies,
+) -> Result<Vec<OutdatedDependency>> {
+    let node_modules = dependencies.root.join("node_modules");
+
+    let locked_versions = match Lockfile::read(&dependencies.root) {
+        Some(lockfile) => lockfile.versions(),
+        None => Default::default(),
+    };
+
+    let registry = dependencies.registry();
+    let mut handles = Vec::new();
+
+    for (name, spec) in dependencies.root_dependencies() {
+        // directories, tarballs and git repositories have no registry versions to compare with
+        let (real_name, range) = match Specifier::parse(&spec).registry_package(&name) {
+            Some(registry_package) => registry_package,
+            None => continue,
+        };
+
+        let current = read_installed_version(&node_modules, &name)
+            .or_else(|| locked_versions.get(&name).cloned());
+
+        let registry = registry.clone();
+        let concurrency = dependencies.concurrency.clone();
+
+        handles.push(tokio::spawn(async move {
+            let permit = concurrency.network().await;
+            let packument = registry.fetch_packument(&real_name).await?;
+            drop(permit);
+
+            let wanted = max_satisfying_version(&packument, &range)?;
+            let latest = match packument["dist-tags"]["latest"].as_str() {
+                Some(latest) => latest.to_string(),
+                None => {
+                    return Err(Error::NoMatchingVersion {
+                        name: real_name,
+                        spec: "latest".to_string(),
+                    })
+                }
+            };
+
+            Ok(OutdatedDependency {
+                name,
+                current,
+                wanted,
+                latest,
+                range: spec,
+            })
+        }));
+    }
+
+    let mut outdated = Vec::new();
+
+    for handle in handles {
+        let dependency = joined(handle.await)?;
+
+        if dependency.is_outdated() {
+            outdated.push(dependency);
+        }
+    }
+
+    Ok(outdated)
+}
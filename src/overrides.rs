@@ -0,0 +1,207 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::utils::split_package_spec;
+use crate::version_range::satisfies;
+
+/// A package name with an optional version range, e.g. the `foo@^1.0.0` in an override key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub name: String,
+    pub range: Option<String>,
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Selector {
+        let (name, range) = split_package_spec(selector);
+
+        Selector {
+            name: name.to_string(),
+            range: range.map(String::from),
+        }
+    }
+
+    fn matches(&self, name: &str, version: &str) -> bool {
+        self.name == name
+            && self
+                .range
+                .as_ref()
+                .map_or(true, |range| satisfies(version, range))
+    }
+}
+
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.range {
+            Some(range) => write!(f, "{}@{}", self.name, range),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideRule {
+    /// Packages that have to appear, in this order, among the ancestors of the target
+    pub parents: Vec<Selector>,
+    pub target: Selector,
+    /// The specifier used instead of the one the parent asked for
+    pub spec: String,
+}
+
+/// The `overrides` (npm) and `resolutions` (yarn) of the root `package.json`, which replace the
+/// specifiers of transitive dependencies before their versions are chosen
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    rules: Vec<OverrideRule>,
+}
+
+impl Overrides {
+    /// Builds the rules from the `overrides` and `resolutions` fields. `root_dependencies` is used
+    /// to expand `$name` references to the specifier of a root dependency
+    pub fn new(
+        overrides: &BTreeMap<String, Value>,
+        resolutions: &BTreeMap<String, String>,
+        root_dependencies: &BTreeMap<String, String>,
+    ) -> Result<Overrides> {
+        let mut rules = Vec::new();
+
+        for (key, value) in overrides {
+            collect_npm_overrides(&[], key, value, root_dependencies, &mut rules)?;
+        }
+
+        for (key, spec) in resolutions {
+            // yarn uses `parent/child` paths where `**` matches any ancestors
+            let mut segments: Vec<String> = Vec::new();
+            for segment in key.split('/') {
+                match segments.last_mut() {
+                    Some(last) if is_scope(last) => *last = format!("{}/{}", last, segment),
+                    _ => segments.push(segment.to_string()),
+                }
+            }
+
+            let target = match segments.pop() {
+                Some(target) => Selector::parse(&target),
+                None => continue,
+            };
+
+            let parents = segments
+                .iter()
+                .filter(|segment| segment.as_str() != "**")
+                .map(|segment| Selector::parse(segment))
+                .collect();
+
+            rules.push(OverrideRule {
+                parents,
+                target,
+                spec: expand_reference(spec, root_dependencies)?,
+            });
+        }
+
+        Ok(Overrides { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Finds the most specific rule for `name` given its ancestors, from the root dependency
+    /// down to its direct parent, as `(name, version)` pairs. A rule only applies when its
+    /// parents appear among the ancestors in the same order, not necessarily next to each other
+    pub fn find(&self, ancestors: &[(String, String)], name: &str) -> Option<&OverrideRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.target.name == name)
+            .filter(|rule| {
+                let mut remaining = ancestors.iter();
+                rule.parents.iter().all(|parent| {
+                    remaining.any(|(ancestor, version)| parent.matches(ancestor, version))
+                })
+            })
+            .max_by_key(|rule| rule.parents.len())
+    }
+
+    /// A flat representation of the rules, stored in the lockfile to notice when they change
+    pub fn to_lock(&self) -> BTreeMap<String, String> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                let mut path: Vec<String> = rule.parents.iter().map(Selector::to_string).collect();
+                path.push(rule.target.to_string());
+
+                (path.join(" > "), rule.spec.clone())
+            })
+            .collect()
+    }
+}
+
+/// npm overrides are either `"name": "spec"` or a nested object where `"."` overrides the
+/// package itself and every other key overrides a package below it
+fn collect_npm_overrides(
+    parents: &[Selector],
+    key: &str,
+    value: &Value,
+    root_dependencies: &BTreeMap<String, String>,
+    rules: &mut Vec<OverrideRule>,
+) -> Result<()> {
+    let target = Selector::parse(key);
+
+    match value {
+        Value::String(spec) => rules.push(OverrideRule {
+            parents: parents.to_vec(),
+            target,
+            spec: expand_reference(spec, root_dependencies)?,
+        }),
+        Value::Object(children) => {
+            let mut nested_parents = parents.to_vec();
+            nested_parents.push(target.clone());
+
+            for (child_key, child_value) in children {
+                if child_key == "." {
+                    if let Some(spec) = child_value.as_str() {
+                        rules.push(OverrideRule {
+                            parents: parents.to_vec(),
+                            target: target.clone(),
+                            spec: expand_reference(spec, root_dependencies)?,
+                        });
+                    }
+                } else {
+                    collect_npm_overrides(
+                        &nested_parents,
+                        child_key,
+                        child_value,
+                        root_dependencies,
+                        rules,
+                    )?;
+                }
+            }
+        }
+        _ => {
+            return Err(Error::InvalidSpec {
+                spec: key.to_string(),
+                reason: "overrides have to be a specifier or an object".to_string(),
+            })
+        }
+    }
+
+    Ok(())
+}
+
+/// Expands `$name` to the specifier the root `package.json` uses for `name`
+fn expand_reference(spec: &str, root_dependencies: &BTreeMap<String, String>) -> Result<String> {
+    match spec.strip_prefix('$') {
+        Some(name) => match root_dependencies.get(name) {
+            Some(spec) => Ok(spec.clone()),
+            None => Err(Error::InvalidSpec {
+                spec: spec.to_string(),
+                reason: "the override references a package that is not a root dependency"
+                    .to_string(),
+            }),
+        },
+        None => Ok(spec.to_string()),
+    }
+}
+
+fn is_scope(segment: &str) -> bool {
+    segment.starts_with('@') && !segment.contains('/')
+}
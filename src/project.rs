@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::workspaces::find_workspaces;
+
+/// Where a command runs: the package it was started in and the project root, which holds
+/// `node_modules` and `blaze.lock`
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// The directory of the root `package.json`. For a workspace this is the directory of the
+    /// `package.json` declaring it, otherwise the same as `package_directory`
+    pub root: PathBuf,
+    /// The directory of the nearest `package.json`
+    pub package_directory: PathBuf,
+}
+
+impl Project {
+    /// Walks up from `start` to the nearest directory with a `package.json`, then keeps walking
+    /// to find a project declaring it as one of its workspaces. Without any `package.json`,
+    /// `start` itself is used so that `blaze install <name>` can create one
+    pub fn discover(start: &Path) -> Result<Project> {
+        let start = fs::canonicalize(start).map_err(Error::io(start))?;
+
+        let package_directory = match start
+            .ancestors()
+            .find(|directory| directory.join("package.json").is_file())
+        {
+            Some(directory) => directory.to_path_buf(),
+            None => {
+                return Ok(Project {
+                    root: start.clone(),
+                    package_directory: start,
+                })
+            }
+        };
+
+        let root = package_directory
+            .ancestors()
+            .skip(1)
+            .filter(|directory| directory.join("package.json").is_file())
+            .find(|directory| declares_workspace(directory, &package_directory))
+            .unwrap_or(&package_directory)
+            .to_path_buf();
+
+        Ok(Project {
+            root,
+            package_directory,
+        })
+    }
+
+    /// Whether the command was started inside a workspace rather than in the project root
+    pub fn is_workspace(&self) -> bool {
+        self.root != self.package_directory
+    }
+}
+
+/// Whether one of the workspaces of the project in `root` lives in `directory`. A malformed
+/// `package.json` further up is not the concern of the package we were started in, so errors
+/// count as no match
+fn declares_workspace(root: &Path, directory: &Path) -> bool {
+    match find_workspaces(root) {
+        Ok(workspaces) => workspaces.iter().any(|workspace| {
+            fs::canonicalize(&workspace.directory).map_or(false, |path| path == directory)
+        }),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::linker::{package_binaries, remove_empty_parents, remove_existing};
+use crate::lockfile::NESTED_SEPARATOR;
+use crate::reporter::{Event, Reporter};
+use crate::resolver::ResolvedGraph;
+use crate::utils::installed_package_names;
+
+/// An entry of `node_modules` that the resolved graph does not account for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extraneous {
+    /// A package directory, relative to `node_modules`, which may be nested in the
+    /// `node_modules` of another package
+    Package(PathBuf),
+    /// A file in a `.bin` directory that no package next to it provides, relative to
+    /// `node_modules`
+    Binary(PathBuf),
+}
+
+impl Extraneous {
+    /// The path of the entry relative to `node_modules`
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Extraneous::Package(path) | Extraneous::Binary(path) => path.clone(),
+        }
+    }
+}
+
+/// Lists the packages and binaries in `node_modules` that are not part of `graph`, such as the
+/// leftovers of packages that were removed by hand or installed on another branch
+pub fn find_extraneous(node_modules: &Path, graph: &ResolvedGraph) -> Vec<Extraneous> {
+    let kept: BTreeSet<&String> = graph
+        .packages
+        .keys()
+        .chain(graph.workspaces.keys())
+        .collect();
+
+    let mut extraneous = Vec::new();
+    collect_extraneous(node_modules, "", &kept, &mut extraneous);
+
+    extraneous.sort();
+    extraneous
+}
+
+/// Adds the extraneous entries of the `node_modules` of the package installed at `parent`, or
+/// of the project when `parent` is empty, to `extraneous`
+fn collect_extraneous(
+    node_modules: &Path,
+    parent: &str,
+    kept: &BTreeSet<&String>,
+    extraneous: &mut Vec<Extraneous>,
+) {
+    let relative = match parent {
+        "" => PathBuf::new(),
+        parent => Path::new(parent).join("node_modules"),
+    };
+    let directory = node_modules.join(&relative);
+
+    let mut binaries = BTreeSet::new();
+
+    for name in installed_package_names(&directory) {
+        let path = match parent {
+            "" => name.clone(),
+            parent => format!("{}{}{}", parent, NESTED_SEPARATOR, name),
+        };
+
+        if !kept.contains(&path) {
+            extraneous.push(Extraneous::Package(relative.join(&name)));
+            continue;
+        }
+
+        let package_directory = directory.join(&name);
+
+        if let Ok(contents) = fs::read_to_string(package_directory.join("package.json")) {
+            let package_json: Value = serde_json::from_str(&contents).unwrap_or_default();
+            binaries.extend(package_binaries(&name, &package_json).into_keys());
+        }
+
+        // workspaces and linked packages are symlinks out of `node_modules`, whose own
+        // `node_modules` is not ours
+        let is_symlink = package_directory
+            .symlink_metadata()
+            .map_or(true, |metadata| metadata.file_type().is_symlink());
+        if !is_symlink {
+            collect_extraneous(node_modules, &path, kept, extraneous);
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(directory.join(".bin")) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            // binaries are shims named `<name>.cmd` on windows
+            let bin_name = file_name.strip_suffix(".cmd").unwrap_or(&file_name);
+
+            if !binaries.contains(bin_name) {
+                extraneous.push(Extraneous::Binary(relative.join(".bin").join(&file_name)));
+            }
+        }
+    }
+}
+
+/// Removes the `find_extraneous` entries of `node_modules` and returns them. Each removed
+/// package is reported as `Event::Removed`
+pub fn prune(
+    node_modules: &Path,
+    graph: &ResolvedGraph,
+    reporter: Arc<dyn Reporter>,
+) -> Result<Vec<Extraneous>> {
+    let extraneous = find_extraneous(node_modules, graph);
+
+    for entry in &extraneous {
+        let path = node_modules.join(entry.path());
+        remove_existing(&path)?;
+
+        if let Extraneous::Package(package) = entry {
+            remove_empty_parents(node_modules, &path);
+
+            reporter.report(Event::Removed {
+                name: package.to_string_lossy().to_string(),
+            });
+        }
+    }
+
+    Ok(extraneous)
+}
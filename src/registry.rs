@@ -0,0 +1,330 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::mpsc;
+
+use crate::error::{Error, Result};
+
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// The gzipped tarball of a package, read as it is downloaded
+pub type Tarball = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Where packuments and tarballs come from. The resolver and installer only talk to the registry
+/// through this trait, so they can be pointed at a mirror, a directory of fixtures or an
+/// in-memory registry
+#[async_trait]
+pub trait Registry: Send + Sync {
+    /// Fetches the packument of a package: its dist-tags and the manifest of every version
+    async fn fetch_packument(&self, package_name: &str) -> Result<Value>;
+
+    /// Fetches the manifest of a single version. Registries that can serve it on its own
+    /// override this to avoid transferring the whole packument
+    async fn fetch_manifest(&self, package_name: &str, version: &str) -> Result<Value> {
+        let packument = self.fetch_packument(package_name).await?;
+
+        match packument["versions"].get(version) {
+            Some(manifest) => Ok(manifest.clone()),
+            None => Err(Error::NoMatchingVersion {
+                name: package_name.to_string(),
+                spec: version.to_string(),
+            }),
+        }
+    }
+
+    /// Starts fetching the tarball of `package_name@version`. `url` is the tarball url from its
+    /// manifest or the lockfile, and may be empty
+    async fn fetch_tarball(&self, package_name: &str, version: &str, url: &str) -> Result<Tarball>;
+}
+
+/// The registry used by the CLI
+pub fn default_registry() -> Arc<dyn Registry> {
+    Arc::new(HttpRegistry::default())
+}
+
+/// The tarball file name the npm registry uses, e.g. `node-18.0.0.tgz` for `@types/node`
+fn tarball_file_name(package_name: &str, version: &str) -> String {
+    let unscoped_name = package_name.rsplit('/').next().unwrap_or(package_name);
+    format!("{}-{}.tgz", unscoped_name, version)
+}
+
+/// A registry speaking the npm registry protocol over HTTP
+#[derive(Debug, Clone)]
+pub struct HttpRegistry {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Default for HttpRegistry {
+    fn default() -> HttpRegistry {
+        HttpRegistry::new(DEFAULT_REGISTRY)
+    }
+}
+
+impl HttpRegistry {
+    pub fn new(url: &str) -> HttpRegistry {
+        HttpRegistry {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Fetches a JSON document, failing with `not_found` when the registry answers 404
+    async fn fetch_json(&self, url: &str, not_found: Error) -> Result<Value> {
+        let response = match self.client.get(url).send().await {
+            Ok(response) => response,
+            Err(error) => return Err(Error::network(url, error)),
+        };
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(not_found);
+        }
+
+        if !response.status().is_success() {
+            return Err(Error::network(url, response.status()));
+        }
+
+        let text = match response.text().await {
+            Ok(text) => text,
+            Err(error) => return Err(Error::network(url, error)),
+        };
+
+        match serde_json::from_str(&text) {
+            Ok(json) => Ok(json),
+            Err(error) => Err(Error::manifest(url, error)),
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for HttpRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> Result<Value> {
+        let url = format!("{}/{}", self.url, package_name);
+
+        self.fetch_json(
+            &url,
+            Error::PackageNotFound {
+                name: package_name.to_string(),
+            },
+        )
+        .await
+    }
+
+    async fn fetch_manifest(&self, package_name: &str, version: &str) -> Result<Value> {
+        let url = format!("{}/{}/{}", self.url, package_name, version);
+
+        self.fetch_json(
+            &url,
+            Error::NoMatchingVersion {
+                name: package_name.to_string(),
+                spec: version.to_string(),
+            },
+        )
+        .await
+    }
+
+    async fn fetch_tarball(&self, package_name: &str, version: &str, url: &str) -> Result<Tarball> {
+        let url = if url.is_empty() {
+            format!(
+                "{}/{}/-/{}",
+                self.url,
+                package_name,
+                tarball_file_name(package_name, version)
+            )
+        } else {
+            url.to_string()
+        };
+
+        let mut response = match self.client.get(&url).send().await {
+            Ok(response) => response,
+            Err(error) => return Err(Error::network(&url, error)),
+        };
+
+        if !response.status().is_success() {
+            return Err(Error::network(&url, response.status()));
+        }
+
+        // the body is downloaded by a task of its own, which stops once the reader is dropped
+        let (sender, receiver) = mpsc::channel(TARBALL_CHUNKS);
+
+        tokio::spawn(async move {
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => Ok(chunk.to_vec()),
+                    Ok(None) => break,
+                    Err(error) => Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        Error::network(&url, error),
+                    )),
+                };
+
+                let failed = chunk.is_err();
+                if sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Box::new(ChunkReader {
+            receiver,
+            chunk: Cursor::new(Vec::new()),
+        }))
+    }
+}
+
+/// How many downloaded chunks of a tarball wait to be read before the download pauses
+const TARBALL_CHUNKS: usize = 16;
+
+/// Reads the chunks of a response body as they are downloaded. A failed download ends with an
+/// `io::Error` wrapping the `Error::Network`
+struct ChunkReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    /// The chunk being read
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let chunk = self.chunk.get_ref();
+            let position = self.chunk.position() as usize;
+
+            if position < chunk.len() {
+                let length = buf.remaining().min(chunk.len() - position);
+                buf.put_slice(&chunk[position..position + length]);
+                self.chunk.set_position((position + length) as u64);
+
+                return Poll::Ready(Ok(()));
+            }
+
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.chunk = Cursor::new(chunk),
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                // the body ended
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// A registry served from a directory laid out like the npm registry: the packument of a package
+/// lives in `<name>/index.json` and its tarballs in `<name>/-/<name>-<version>.tgz`, where scoped
+/// names keep their `@scope/` directory. Tarball urls are ignored
+#[derive(Debug, Clone)]
+pub struct LocalRegistry {
+    directory: PathBuf,
+}
+
+impl LocalRegistry {
+    pub fn new(directory: impl Into<PathBuf>) -> LocalRegistry {
+        LocalRegistry {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Registry for LocalRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> Result<Value> {
+        let path = self.directory.join(package_name).join("index.json");
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                return Err(Error::PackageNotFound {
+                    name: package_name.to_string(),
+                })
+            }
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(packument) => Ok(packument),
+            Err(error) => Err(Error::manifest(path.display(), error)),
+        }
+    }
+
+    async fn fetch_tarball(
+        &self,
+        package_name: &str,
+        version: &str,
+        _url: &str,
+    ) -> Result<Tarball> {
+        let path = self
+            .directory
+            .join(package_name)
+            .join("-")
+            .join(tarball_file_name(package_name, version));
+
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => Ok(Box::new(file)),
+            Err(error) => Err(Error::io(&path)(error)),
+        }
+    }
+}
+
+/// A registry holding its packuments and tarballs in memory, for tests and tools that build
+/// their own package graph
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRegistry {
+    packuments: HashMap<String, Value>,
+    tarballs: HashMap<(String, String), Vec<u8>>,
+}
+
+impl MemoryRegistry {
+    pub fn new() -> MemoryRegistry {
+        MemoryRegistry::default()
+    }
+
+    /// Adds a packument, keyed by its `name` field
+    pub fn add_packument(&mut self, packument: Value) {
+        let name = packument["name"].as_str().unwrap_or_default().to_string();
+        self.packuments.insert(name, packument);
+    }
+
+    pub fn add_tarball(&mut self, package_name: &str, version: &str, tarball: Vec<u8>) {
+        self.tarballs
+            .insert((package_name.to_string(), version.to_string()), tarball);
+    }
+}
+
+#[async_trait]
+impl Registry for MemoryRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> Result<Value> {
+        match self.packuments.get(package_name) {
+            Some(packument) => Ok(packument.clone()),
+            None => Err(Error::PackageNotFound {
+                name: package_name.to_string(),
+            }),
+        }
+    }
+
+    async fn fetch_tarball(
+        &self,
+        package_name: &str,
+        version: &str,
+        _url: &str,
+    ) -> Result<Tarball> {
+        match self
+            .tarballs
+            .get(&(package_name.to_string(), version.to_string()))
+        {
+            Some(tarball) => Ok(Box::new(Cursor::new(tarball.clone()))),
+            None => Err(Error::NoMatchingVersion {
+                name: package_name.to_string(),
+                spec: version.to_string(),
+            }),
+        }
+    }
+}
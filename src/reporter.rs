@@ -0,0 +1,81 @@
+/// Something that happened while installing. The resolver, fetcher and linker report their
+/// progress through these instead of printing, so embedders decide what to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    ResolveStarted,
+    Resolved {
+        name: String,
+        version: String,
+    },
+    ResolveFinished {
+        packages: usize,
+    },
+    FetchStarted {
+        packages: usize,
+    },
+    /// A package is in the store or its source directory is ready. `bytes` is the size of the
+    /// downloaded tarball, zero when nothing was downloaded
+    Fetched {
+        name: String,
+        version: String,
+        bytes: u64,
+        cached: bool,
+    },
+    FetchFinished,
+    LinkStarted {
+        packages: usize,
+    },
+    Linked {
+        name: String,
+    },
+    LinkFinished,
+    /// A package that is no longer depended on was removed from `node_modules`
+    Removed {
+        name: String,
+    },
+    /// The lifecycle scripts of the project, such as `postinstall`, are about to run
+    ScriptsStarted {
+        scripts: usize,
+    },
+    /// A script of `package` is about to run `command`, either as a lifecycle script of an
+    /// install or through `Scripts::run`. Scripts print to the terminal themselves
+    ScriptStarted {
+        package: String,
+        script: String,
+        command: String,
+    },
+    ScriptFinished {
+        package: String,
+        script: String,
+        code: i32,
+    },
+    ScriptsFinished,
+    /// A message for the user that is not tied to a phase, such as the lockfile being imported
+    Info {
+        message: String,
+    },
+    /// A problem that does not stop the install
+    Warning {
+        message: String,
+    },
+}
+
+/// Receives the events of an install. Events come from several tasks at once, so reporters have
+/// to be shareable between threads
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: Event);
+}
+
+impl<F: Fn(Event) + Send + Sync> Reporter for F {
+    fn report(&self, event: Event) {
+        self(event)
+    }
+}
+
+/// A reporter dropping every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {
+    fn report(&self, _event: Event) {}
+}
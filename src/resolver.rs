@@ -0,0 +1,632 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+use crate::concurrency::Concurrency;
+use crate::dependencies::max_satisfying_version;
+use crate::error::{Error, PackageFailure, Result, Stage};
+use crate::lockfile::{
+    find_installed, installed_name, parent_path, LockedPackage, NESTED_SEPARATOR,
+};
+use crate::overrides::Overrides;
+use crate::registry::Registry;
+use crate::reporter::{Event, Reporter, SilentReporter};
+use crate::sources::{fetch_source, shasum_to_integrity, sources_directory, FetchedSource};
+use crate::specifier::Specifier;
+use crate::utils::{joined, validate_package_name};
+use crate::version_range::satisfies;
+
+/// The packages a project resolves to, ready to be fetched and linked
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedGraph {
+    /// Every resolved package keyed by the path it is installed at, relative to `node_modules`,
+    /// such as `a` or `a/node_modules/b` for a copy of `b` nested in `a`
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Workspace packages to link into `node_modules`, mapped to their directories relative to
+    /// the project root. Packages of the same name in `packages` take precedence
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// The packages that were fetched from a directory, tarball url or git repository to resolve
+    /// them, keyed like `packages`, so that the fetcher does not fetch them a second time. A
+    /// graph read back from a lockfile has none
+    #[serde(skip)]
+    pub sources: BTreeMap<String, FetchedSource>,
+}
+
+impl ResolvedGraph {
+    /// The part of the graph that `names`, installed at the top of `node_modules`, depend on,
+    /// directly or not, along with every workspace
+    pub fn reachable_from(&self, names: &BTreeSet<String>) -> ResolvedGraph {
+        let mut reachable = ResolvedGraph {
+            workspaces: self.workspaces.clone(),
+            ..ResolvedGraph::default()
+        };
+        let mut queue: Vec<String> = names.iter().cloned().collect();
+
+        while let Some(path) = queue.pop() {
+            let package = match self.packages.get(&path) {
+                Some(package) if !reachable.packages.contains_key(&path) => package,
+                _ => continue,
+            };
+
+            queue.extend(
+                package
+                    .dependencies
+                    .keys()
+                    .filter_map(|name| find_installed(&self.packages, &path, name)),
+            );
+            reachable.packages.insert(path.clone(), package.clone());
+
+            if let Some(source) = self.sources.get(&path) {
+                reachable.sources.insert(path, source.clone());
+            }
+        }
+
+        reachable
+    }
+}
+
+/// Turns the dependencies of a project into a `ResolvedGraph` by asking the registry for the
+/// versions matching each range
+#[derive(Clone)]
+pub struct Resolver {
+    registry: Arc<dyn Registry>,
+    /// The project root, which local paths are relative to
+    root: PathBuf,
+    overrides: Overrides,
+    /// Versions to keep when they still satisfy the requested range, such as the versions of a
+    /// lockfile
+    preferred_versions: Arc<BTreeMap<String, String>>,
+    reporter: Arc<dyn Reporter>,
+    concurrency: Concurrency,
+    cache: Arc<FetchCache>,
+    /// The blaze cache that sources are fetched into, the default one when unset
+    cache_directory: Option<PathBuf>,
+}
+
+/// The registry documents fetched while resolving, so that no packument or manifest is requested
+/// twice. Requests for a document that is still in flight wait for that fetch instead of
+/// starting another one
+#[derive(Default)]
+struct FetchCache {
+    packuments: Mutex<HashMap<String, CachedDocument>>,
+    manifests: Mutex<HashMap<(String, String), CachedDocument>>,
+}
+
+/// A registry document, empty until its fetch completes
+type CachedDocument = Arc<OnceCell<Arc<Value>>>;
+
+/// The cell holding the document for `key`, created empty the first time it is asked for
+fn cache_cell<K: Eq + Hash>(cells: &Mutex<HashMap<K, CachedDocument>>, key: K) -> CachedDocument {
+    cells.lock().unwrap().entry(key).or_default().clone()
+}
+
+impl Resolver {
+    pub fn new(registry: Arc<dyn Registry>, root: &Path) -> Resolver {
+        Resolver {
+            registry,
+            root: root.to_path_buf(),
+            overrides: Overrides::default(),
+            preferred_versions: Arc::new(BTreeMap::new()),
+            reporter: Arc::new(SilentReporter),
+            concurrency: Concurrency::default(),
+            cache: Arc::default(),
+            cache_directory: None,
+        }
+    }
+
+    pub fn with_overrides(mut self, overrides: Overrides) -> Resolver {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn with_preferred_versions(
+        mut self,
+        preferred_versions: BTreeMap<String, String>,
+    ) -> Resolver {
+        self.preferred_versions = Arc::new(preferred_versions);
+        self
+    }
+
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Resolver {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Shares the network limit with the other phases of an install
+    pub fn with_concurrency(mut self, concurrency: Concurrency) -> Resolver {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_cache_directory(mut self, cache_directory: &Path) -> Resolver {
+        self.cache_directory = Some(cache_directory.to_path_buf());
+        self
+    }
+
+    /// Resolves `dependencies`, mapping names to specifiers, along with everything they depend
+    /// on. Resolving goes on when a dependency fails, and every failure is returned together as
+    /// an `Error::Install` once nothing is left to resolve.
+    ///
+    /// The graph is walked breadth-first, one level at a time. The packages of a level are
+    /// fetched in parallel, but their results are taken in the order they were queued, so the
+    /// first request for an install path always wins no matter which response arrives first.
+    /// The direct dependencies come first, in the order of their names.
+    ///
+    /// A request is satisfied by the package its dependent finds in `node_modules` when that
+    /// package is in its range. Otherwise the package is installed at the top of `node_modules`
+    /// when nothing is there yet, and nested in the `node_modules` of the dependent when another
+    /// version is. Requests for a path another request of the level is still resolving wait for
+    /// the next level. Only registry ranges can be checked against a version, so requests with
+    /// any other specifier use the package they find.
+    ///
+    /// Overrides are applied to every request before it is placed, and an overridden request
+    /// takes the place of one that is not. When an override cannot take effect because the
+    /// dependent is linked from elsewhere and has no `node_modules` to nest into, a warning is
+    /// reported.
+    ///
+    /// Dependency cycles end where they loop back to a package that is already resolved, and a
+    /// package depending back on a version between it and the project uses the copy it finds
+    /// rather than being nested once more
+    pub async fn resolve(&self, dependencies: &BTreeMap<String, String>) -> Result<ResolvedGraph> {
+        self.reporter.report(Event::ResolveStarted);
+
+        let mut graph = ResolvedGraph::default();
+        let mut failures = Vec::new();
+
+        let mut queue: Vec<Request> = dependencies
+            .iter()
+            .map(|(name, spec)| Request {
+                name: name.clone(),
+                spec: spec.clone(),
+                parent: String::new(),
+                path: String::new(),
+                ancestors: Vec::new(),
+                overridden: false,
+            })
+            .collect();
+
+        while !queue.is_empty() {
+            let mut level: Vec<Request> = Vec::new();
+            // the install paths the requests of this level are resolved at
+            let mut placed: HashMap<String, usize> = HashMap::new();
+
+            for original in std::mem::take(&mut queue) {
+                let mut request = original.clone();
+
+                let spec = match self
+                    .apply_overrides(&request.name, request.spec.clone(), &request.ancestors)
+                    .await
+                {
+                    Ok(spec) => spec,
+                    Err(error) => {
+                        failures.push(PackageFailure {
+                            package: format!("{}@{}", request.name, request.spec),
+                            stage: Stage::Resolve,
+                            error,
+                        });
+                        continue;
+                    }
+                };
+                request.overridden = spec != request.spec;
+                request.spec = spec;
+
+                match self.place(&graph, &placed, &request) {
+                    Placement::Satisfied => (),
+                    Placement::At(path) => {
+                        request.path = path.clone();
+                        placed.insert(path, level.len());
+                        level.push(request);
+                    }
+                    Placement::Waiting(index) if request.overridden && !level[index].overridden => {
+                        request.path = level[index].path.clone();
+                        let displaced = std::mem::replace(&mut level[index], request);
+                        queue.push(displaced);
+                    }
+                    Placement::Waiting(_) => queue.push(original),
+                    Placement::Shadowed(installed) => {
+                        if request.overridden {
+                            self.warn_override_lost(&request, &installed);
+                        }
+                    }
+                }
+            }
+
+            let handles: Vec<_> = level
+                .iter()
+                .map(|request| tokio::spawn(resolve_package(self.clone(), request.clone())))
+                .collect();
+
+            for (request, handle) in level.into_iter().zip(handles) {
+                let (package, source) = match joined(handle.await) {
+                    Ok(resolved) => resolved,
+                    Err(error) => {
+                        failures.push(PackageFailure {
+                            package: format!("{}@{}", request.name, request.spec),
+                            stage: Stage::Resolve,
+                            error,
+                        });
+                        continue;
+                    }
+                };
+
+                let mut ancestors = request.ancestors;
+                ancestors.push((request.name.clone(), package.version.clone()));
+
+                for (name, spec) in &package.dependencies {
+                    queue.push(Request {
+                        name: name.clone(),
+                        spec: spec.clone(),
+                        parent: request.path.clone(),
+                        path: String::new(),
+                        ancestors: ancestors.clone(),
+                        overridden: false,
+                    });
+                }
+
+                if let Some(source) = source {
+                    graph.sources.insert(request.path.clone(), source);
+                }
+                graph.packages.insert(request.path, package);
+            }
+        }
+
+        self.reporter.report(Event::ResolveFinished {
+            packages: graph.packages.len(),
+        });
+
+        if !failures.is_empty() {
+            return Err(Error::Install { failures });
+        }
+
+        Ok(graph)
+    }
+
+    /// Decides where `request` is installed, given the packages resolved so far and the
+    /// install paths `placed` in the current level
+    fn place(
+        &self,
+        graph: &ResolvedGraph,
+        placed: &HashMap<String, usize>,
+        request: &Request,
+    ) -> Placement {
+        let registry_package = Specifier::parse(&request.spec).registry_package(&request.name);
+        let accepts = |package: &LockedPackage| match &registry_package {
+            Some((name, range)) => *name == package.name && satisfies(&package.version, range),
+            None => true,
+        };
+
+        // the paths Node.js looks for the package at, from the dependent up to the top
+        let mut paths = Vec::new();
+        let mut parent = Some(request.parent.as_str()).filter(|parent| !parent.is_empty());
+        while let Some(directory) = parent {
+            paths.push(format!("{}{}{}", directory, NESTED_SEPARATOR, request.name));
+            parent = parent_path(directory);
+        }
+        paths.push(request.name.clone());
+
+        for path in &paths {
+            if let Some(&index) = placed.get(path) {
+                return Placement::Waiting(index);
+            }
+
+            if let Some(package) = graph.packages.get(path) {
+                if accepts(package) {
+                    return Placement::Satisfied;
+                }
+
+                return match self.nested_path(graph, request) {
+                    Some(nested) => Placement::At(nested),
+                    None => Placement::Shadowed(format!("{}@{}", package.name, package.version)),
+                };
+            }
+        }
+
+        // the top of `node_modules` is kept for the requests that accept the version preferred
+        // there, so that the others do not take it from them
+        let keeps_top = match (
+            &registry_package,
+            self.preferred_versions.get(&request.name),
+        ) {
+            (Some((_, range)), Some(version)) => !satisfies(version, range),
+            _ => false,
+        };
+
+        match self.nested_path(graph, request) {
+            Some(nested) if keeps_top => Placement::At(nested),
+            _ => Placement::At(request.name.clone()),
+        }
+    }
+
+    /// The path of `request` in the `node_modules` of its dependent, if it can be nested there
+    fn nested_path(&self, graph: &ResolvedGraph, request: &Request) -> Option<String> {
+        let parent = graph.packages.get(&request.parent)?;
+
+        // linked packages live outside of `node_modules`, along with their own `node_modules`
+        if matches!(Specifier::parse(&parent.resolved), Specifier::Link(_)) {
+            return None;
+        }
+
+        let loops_back = match Specifier::parse(&request.spec).registry_package(&request.name) {
+            Some((_, range)) => request
+                .ancestors
+                .iter()
+                .any(|(name, version)| *name == request.name && satisfies(version, &range)),
+            None => false,
+        };
+        if loops_back {
+            return None;
+        }
+
+        Some(format!(
+            "{}{}{}",
+            request.parent, NESTED_SEPARATOR, request.name
+        ))
+    }
+
+    /// Resolves the version of `real_name` to install at `path`, whose last part differs from
+    /// `real_name` for `npm:` aliases. A version preferred for the path itself comes before one
+    /// preferred for the top of `node_modules`
+    async fn resolve_version(
+        &self,
+        path: &str,
+        real_name: &str,
+        version_req: &str,
+    ) -> Result<String> {
+        let preferred = self
+            .preferred_versions
+            .get(path)
+            .or_else(|| self.preferred_versions.get(installed_name(path)));
+
+        if let Some(version) = preferred {
+            if satisfies(version, version_req) {
+                return Ok(version.clone());
+            }
+        }
+
+        let packument = self.packument(real_name).await?;
+
+        max_satisfying_version(&packument, version_req)
+    }
+
+    async fn packument(&self, package_name: &str) -> Result<Arc<Value>> {
+        let cell = cache_cell(&self.cache.packuments, package_name.to_string());
+
+        let packument = cell
+            .get_or_try_init(|| async {
+                let _permit = self.concurrency.network().await;
+
+                self.registry
+                    .fetch_packument(package_name)
+                    .await
+                    .map(Arc::new)
+            })
+            .await?;
+
+        Ok(packument.clone())
+    }
+
+    /// The manifest of `package_name@version`, taken from its packument when that was fetched
+    /// already
+    async fn manifest(&self, package_name: &str, version: &str) -> Result<Arc<Value>> {
+        let cell = cache_cell(
+            &self.cache.manifests,
+            (package_name.to_string(), version.to_string()),
+        );
+
+        let manifest = cell
+            .get_or_try_init(|| async {
+                let packument = self
+                    .cache
+                    .packuments
+                    .lock()
+                    .unwrap()
+                    .get(package_name)
+                    .and_then(|cell| cell.get().cloned());
+
+                if let Some(manifest) =
+                    packument.and_then(|packument| packument["versions"].get(version).cloned())
+                {
+                    return Ok(Arc::new(manifest));
+                }
+
+                let _permit = self.concurrency.network().await;
+
+                self.registry
+                    .fetch_manifest(package_name, version)
+                    .await
+                    .map(Arc::new)
+            })
+            .await?;
+
+        Ok(manifest.clone())
+    }
+
+    /// Reports that the override applied to `request` is not installed, since `installed` won
+    fn warn_override_lost(&self, request: &Request, installed: &str) {
+        let mut path: Vec<&str> = request
+            .ancestors
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        path.push(&request.name);
+
+        self.reporter.report(Event::Warning {
+            message: format!(
+                "the override of {} to {} is not applied, {} is installed instead",
+                path.join(" > "),
+                request.spec,
+                installed
+            ),
+        });
+    }
+
+    /// Replaces the specifier of a transitive dependency when an override applies to it.
+    /// Overrides targeting a version range only apply when the version the original specifier
+    /// resolves to is in that range. The direct dependencies of the project are never overridden
+    async fn apply_overrides(
+        &self,
+        package_name: &str,
+        spec: String,
+        ancestors: &[(String, String)],
+    ) -> Result<String> {
+        if ancestors.is_empty() || self.overrides.is_empty() {
+            return Ok(spec);
+        }
+
+        let rule = match self.overrides.find(ancestors, package_name) {
+            Some(rule) => rule,
+            None => return Ok(spec),
+        };
+
+        let target_range = match &rule.target.range {
+            Some(target_range) => target_range,
+            None => return Ok(rule.spec.clone()),
+        };
+
+        let (real_name, version_req) = match Specifier::parse(&spec).registry_package(package_name)
+        {
+            Some(registry_package) => registry_package,
+            None => return Ok(spec),
+        };
+
+        let version = self
+            .resolve_version(package_name, &real_name, &version_req)
+            .await?;
+
+        if satisfies(&version, target_range) {
+            Ok(rule.spec.clone())
+        } else {
+            Ok(spec)
+        }
+    }
+}
+
+/// A dependency waiting to be resolved
+#[derive(Debug, Clone)]
+struct Request {
+    name: String,
+    spec: String,
+    /// The install path of the dependent, empty for the dependencies of the project
+    parent: String,
+    /// The install path the request is resolved at, once it is placed
+    path: String,
+    /// The `(name, version)` of every package between the project and this dependency, which
+    /// decides the overrides that apply to it
+    ancestors: Vec<(String, String)>,
+    /// Whether `spec` comes from an override rather than from the parent
+    overridden: bool,
+}
+
+/// Where a request goes, as decided by `Resolver::place`
+enum Placement {
+    /// The package the dependent finds in `node_modules` is in range
+    Satisfied,
+    /// The request is resolved at this install path
+    At(String),
+    /// The package the dependent would find is resolved by the request at this index of the
+    /// level, so the request waits for the next level
+    Waiting(usize),
+    /// The package the dependent finds is out of range but cannot be shadowed, and is used
+    /// anyway. Holds its `name@version`
+    Shadowed(String),
+}
+
+/// Resolves a single package to the version to install, filling in the ranges of its own
+/// dependencies without resolving them. Packages that are not on the registry are fetched to
+/// read their manifest, and come with the source they were fetched from
+async fn resolve_package(
+    resolver: Resolver,
+    request: Request,
+) -> Result<(LockedPackage, Option<FetchedSource>)> {
+    let Request {
+        name: package_name,
+        spec,
+        path,
+        ..
+    } = request;
+
+    validate_package_name(&package_name)?;
+
+    let specifier = Specifier::parse(&spec);
+
+    let (mut package, json, source) = match specifier.registry_package(&package_name) {
+        Some((real_name, version_req)) => {
+            let version = resolver
+                .resolve_version(&path, &real_name, &version_req)
+                .await?;
+            let json = resolver.manifest(&real_name, &version).await?;
+
+            let integrity = match json["dist"]["integrity"].as_str() {
+                Some(integrity) => integrity.to_string(),
+                None => json["dist"]["shasum"]
+                    .as_str()
+                    .and_then(shasum_to_integrity)
+                    .unwrap_or_default(),
+            };
+
+            let package = LockedPackage {
+                name: real_name,
+                version,
+                resolved: json["dist"]["tarball"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                integrity,
+                dependencies: BTreeMap::new(),
+            };
+
+            (package, json, None)
+        }
+        None => {
+            let permit = resolver.concurrency.source(&specifier).await;
+            let sources = sources_directory(resolver.cache_directory.as_deref())?;
+            let source = fetch_source(&specifier, "", &resolver.root, &sources).await?;
+            drop(permit);
+
+            let package = LockedPackage {
+                name: source.manifest["name"]
+                    .as_str()
+                    .unwrap_or(&package_name)
+                    .to_string(),
+                version: source.manifest["version"]
+                    .as_str()
+                    .unwrap_or("0.0.0")
+                    .to_string(),
+                resolved: source.resolved.clone(),
+                integrity: source.integrity.clone(),
+                dependencies: BTreeMap::new(),
+            };
+
+            (package, Arc::new(source.manifest.clone()), Some(source))
+        }
+    };
+
+    resolver.reporter.report(Event::Resolved {
+        name: package_name.clone(),
+        version: package.version.clone(),
+    });
+
+    if let Some(dependencies) = json["dependencies"].as_object() {
+        for (dependency_name, dependency_spec) in dependencies {
+            let dependency_spec = match dependency_spec.as_str() {
+                Some(spec) => spec,
+                None => {
+                    return Err(Error::manifest(
+                        format!("{}@{}", package.name, package.version),
+                        format!("the range of {} is not a string", dependency_name),
+                    ))
+                }
+            };
+
+            package
+                .dependencies
+                .insert(dependency_name.clone(), dependency_spec.to_string());
+        }
+    }
+
+    Ok((package, source))
+}
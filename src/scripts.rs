@@ -0,0 +1,231 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::reporter::{Event, Reporter, SilentReporter};
+
+pub struct Scripts {
+    package_json: Value,
+    /// The directory the scripts are run in
+    directory: PathBuf,
+    pub scripts: BTreeMap<String, String>,
+    reporter: Arc<dyn Reporter>,
+}
+
+impl Scripts {
+    /// Reads the scripts of the package in `directory`, such as the project root or a workspace
+    pub fn from_directory(directory: &Path) -> Result<Scripts> {
+        let path = directory.join("package.json");
+        let contents = fs::read_to_string(&path).map_err(Error::io(&path))?;
+
+        let package_json: Value = match serde_json::from_str(&contents) {
+            Ok(package_json) => package_json,
+            Err(error) => return Err(Error::manifest(path.display(), error)),
+        };
+
+        let mut scripts: BTreeMap<String, String> = match package_json["scripts"].as_object() {
+            Some(scripts) => scripts
+                .iter()
+                .filter_map(|(name, script)| Some((name.clone(), script.as_str()?.to_string())))
+                .collect(),
+            None => BTreeMap::new(),
+        };
+
+        // like npm, `start` falls back to running `server.js` when it is not defined
+        if !scripts.contains_key("start") && directory.join("server.js").exists() {
+            scripts.insert("start".to_string(), "node server.js".to_string());
+        }
+
+        Ok(Scripts {
+            package_json,
+            directory: directory.to_path_buf(),
+            scripts,
+            reporter: Arc::new(SilentReporter),
+        })
+    }
+
+    /// Reports every script before it runs and once it exited
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Scripts {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Runs `pre<name>`, `<name>` and `post<name>` in order, stopping at the first one that
+    /// fails. `args` are only passed to the main script. Returns the exit code of the last
+    /// script that ran
+    pub fn run(&self, name: &str, args: &[String]) -> Result<i32> {
+        let script = match self.scripts.get(name) {
+            Some(script) => script,
+            None => {
+                return Err(Error::MissingScript {
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        let pre_script = format!("pre{}", name);
+        if let Some(script) = self.scripts.get(&pre_script) {
+            let code = self.run_lifecycle_event(&pre_script, script)?;
+            if code != 0 {
+                return Ok(code);
+            }
+        }
+
+        let mut command = script.clone();
+        for arg in args {
+            command.push(' ');
+            command.push_str(&quote_argument(arg));
+        }
+
+        let code = self.run_lifecycle_event(name, &command)?;
+        if code != 0 {
+            return Ok(code);
+        }
+
+        let post_script = format!("post{}", name);
+        if let Some(script) = self.scripts.get(&post_script) {
+            return self.run_lifecycle_event(&post_script, script);
+        }
+
+        Ok(0)
+    }
+
+    /// Runs only the script `name`, without its `pre` and `post` scripts. Returns `None` when
+    /// the script is not defined
+    pub fn run_lifecycle(&self, name: &str) -> Result<Option<i32>> {
+        match self.scripts.get(name) {
+            Some(script) => self.run_lifecycle_event(name, script).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The name and version of the package, as shown before each script it runs
+    pub fn package(&self) -> String {
+        let package_name = self.package_json["name"].as_str().unwrap_or_default();
+        let package_version = self.package_json["version"].as_str().unwrap_or_default();
+
+        format!("{}@{}", package_name, package_version)
+    }
+
+    fn run_lifecycle_event(&self, event: &str, script: &str) -> Result<i32> {
+        self.reporter.report(Event::ScriptStarted {
+            package: self.package(),
+            script: event.to_string(),
+            command: script.to_string(),
+        });
+
+        let mut command = shell_command(script);
+
+        command.current_dir(&self.directory);
+        command.env("PATH", path_with_bin_directories(&self.directory)?);
+        command.env("npm_lifecycle_event", event);
+        command.env("npm_lifecycle_script", script);
+
+        for (key, value) in package_environment(&self.package_json) {
+            command.env(key, value);
+        }
+
+        let status = match command.status() {
+            Ok(status) => status,
+            Err(error) => {
+                return Err(Error::Command {
+                    command: script.to_string(),
+                    source: error,
+                })
+            }
+        };
+
+        // a missing exit code means the script was killed by a signal
+        let code = status.code().unwrap_or(1);
+
+        self.reporter.report(Event::ScriptFinished {
+            package: self.package(),
+            script: event.to_string(),
+            code,
+        });
+
+        Ok(code)
+    }
+}
+
+#[cfg(windows)]
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/d").arg("/s").arg("/c").arg(script);
+    command
+}
+
+#[cfg(not(windows))]
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+#[cfg(windows)]
+fn quote_argument(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('"', "\"\""))
+}
+
+#[cfg(not(windows))]
+fn quote_argument(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Prepends the `node_modules/.bin` directory of `directory` and every one of its ancestors to
+/// `PATH`, nearest first
+pub(crate) fn path_with_bin_directories(directory: &Path) -> Result<std::ffi::OsString> {
+    let directory = directory.canonicalize().map_err(Error::io(directory))?;
+
+    let mut paths: Vec<PathBuf> = directory
+        .ancestors()
+        .map(|directory| directory.join("node_modules").join(".bin"))
+        .collect();
+
+    if let Some(path) = env::var_os("PATH") {
+        paths.extend(env::split_paths(&path));
+    }
+
+    match env::join_paths(paths) {
+        Ok(path) => Ok(path),
+        Err(error) => Err(Error::Io {
+            path: directory,
+            source: std::io::Error::new(std::io::ErrorKind::InvalidInput, error),
+        }),
+    }
+}
+
+/// Flattens `package.json` into `npm_package_*` variables, e.g. `npm_package_scripts_test`
+fn package_environment(package_json: &Value) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    flatten_package_json("npm_package", package_json, &mut variables);
+    variables
+}
+
+fn flatten_package_json(prefix: &str, value: &Value, variables: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let key: String = key
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect();
+                flatten_package_json(&format!("{}_{}", prefix, key), value, variables);
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                flatten_package_json(&format!("{}_{}", prefix, index), value, variables);
+            }
+        }
+        Value::String(string) => variables.push((prefix.to_string(), string.clone())),
+        Value::Number(number) => variables.push((prefix.to_string(), number.to_string())),
+        Value::Bool(boolean) => variables.push((prefix.to_string(), boolean.to_string())),
+        Value::Null => (),
+    }
+}
@@ -0,0 +1,554 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::error::{Error, Result};
+use crate::specifier::Specifier;
+use crate::utils::{default_cache_directory, joined};
+
+/// Tells apart the staging directories of git repositories fetched at the same time
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The locks of the git mirrors in use, by repository url
+static MIRROR_LOCKS: Mutex<Vec<(String, Arc<Mutex<()>>)>> = Mutex::new(Vec::new());
+
+/// A package fetched from a source other than the registry, staged on disk and ready to be
+/// copied into `node_modules`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedSource {
+    /// The directory holding the contents of the package
+    pub directory: PathBuf,
+    /// A specifier pinning the exact source, e.g. a git url with the commit that was checked out
+    pub resolved: String,
+    /// The subresource integrity of the tarball, empty for directories and git repositories
+    pub integrity: String,
+    pub manifest: Value,
+}
+
+/// Fetches a directory, tarball or git repository. Registry specifiers are handled by
+/// `dependencies` and are rejected here. When `integrity` is not empty, tarballs have to match it.
+/// Relative local paths are resolved against `root`, but stay relative in `resolved`. Tarballs
+/// and git repositories are unpacked into `sources`, a directory of the blaze cache
+pub async fn fetch_source(
+    specifier: &Specifier,
+    integrity: &str,
+    root: &Path,
+    sources: &Path,
+) -> Result<FetchedSource> {
+    match specifier {
+        Specifier::Registry(range) | Specifier::Alias { range, .. } => Err(Error::InvalidSpec {
+            spec: range.clone(),
+            reason: "registry specifiers cannot be fetched as a source".to_string(),
+        }),
+        Specifier::Directory(path) => fetch_directory(root, path, "file"),
+        Specifier::Link(path) => fetch_directory(root, path, "link"),
+        Specifier::LocalTarball(path) => {
+            let full_path = root.join(path);
+            let bytes = fs::read(&full_path).map_err(Error::io(&full_path))?;
+
+            fetch_tarball(
+                &bytes,
+                format!("file:{}", path.display()),
+                integrity,
+                sources,
+            )
+        }
+        Specifier::RemoteTarball(url) => {
+            let response = match reqwest::get(url).await {
+                Ok(response) => response,
+                Err(error) => return Err(Error::network(url, error)),
+            };
+
+            if !response.status().is_success() {
+                return Err(Error::network(url, response.status()));
+            }
+
+            let bytes = match response.bytes().await {
+                Ok(bytes) => bytes,
+                Err(error) => return Err(Error::network(url, error)),
+            };
+
+            fetch_tarball(&bytes, url.clone(), integrity, sources)
+        }
+        Specifier::Git { url, committish } => {
+            let (url, committish) = (url.clone(), committish.clone());
+            let sources = sources.to_path_buf();
+
+            joined(
+                tokio::task::spawn_blocking(move || {
+                    fetch_git(&url, committish.as_deref(), &sources)
+                })
+                .await,
+            )
+        }
+    }
+}
+
+/// The directory of the blaze cache `cache_directory` that sources are fetched into, in the
+/// default cache directory when none is given
+pub fn sources_directory(cache_directory: Option<&Path>) -> Result<PathBuf> {
+    match cache_directory {
+        Some(cache_directory) => Ok(cache_directory.join("sources")),
+        None => Ok(default_cache_directory()?.join("sources")),
+    }
+}
+
+fn fetch_directory(root: &Path, path: &Path, protocol: &str) -> Result<FetchedSource> {
+    let directory = root.join(path);
+    let manifest = read_manifest(&directory)?;
+
+    Ok(FetchedSource {
+        directory,
+        resolved: format!("{}:{}", protocol, path.display()),
+        integrity: String::new(),
+        manifest,
+    })
+}
+
+fn fetch_tarball(
+    bytes: &[u8],
+    resolved: String,
+    integrity: &str,
+    sources: &Path,
+) -> Result<FetchedSource> {
+    if !integrity.is_empty() {
+        verify_integrity(bytes, integrity, &resolved)?;
+    }
+
+    let integrity = compute_integrity(bytes);
+    let directory = sources.join(hex_digest(integrity.as_bytes()));
+
+    if directory.exists() {
+        fs::remove_dir_all(&directory).map_err(Error::io(&directory))?;
+    }
+
+    unpack_tarball(bytes, &directory)?;
+
+    let manifest = read_manifest(&directory)?;
+
+    Ok(FetchedSource {
+        directory,
+        resolved,
+        integrity,
+        manifest,
+    })
+}
+
+/// Fetches a git repository at `committish`, falling back to its default branch. Every
+/// repository is mirrored once in the cache, and every commit is exported from the mirror into
+/// a directory of its own, so fetches of different commits never share a checkout. This runs
+/// git and blocks, so it has to be called from `spawn_blocking`
+fn fetch_git(url: &str, committish: Option<&str>, sources: &Path) -> Result<FetchedSource> {
+    fs::create_dir_all(sources).map_err(Error::io(sources))?;
+    let name = format!("git-{}", hex_digest(url.as_bytes()));
+    let mirror = sources.join(format!("{}.git", name));
+
+    // a commit that was exported before needs no fetch, which keeps locked installs offline
+    let is_commit = |committish: &&str| {
+        committish.len() == 40 && committish.chars().all(|c| c.is_ascii_hexdigit())
+    };
+    let exported = committish
+        .filter(is_commit)
+        .map(|commit| {
+            (
+                commit.to_string(),
+                sources.join(format!("{}-{}", name, commit)),
+            )
+        })
+        .filter(|(_, directory)| directory.is_dir());
+
+    let (commit, directory) = match exported {
+        Some(exported) => exported,
+        None => {
+            let commit = {
+                let lock = mirror_lock(url);
+                let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+                update_mirror(url, sources, &mirror)?;
+                find_commit(url, &mirror, committish)?
+            };
+
+            let directory = sources.join(format!("{}-{}", name, commit));
+            if !directory.is_dir() {
+                export_commit(url, &mirror, &commit, sources, &directory)?;
+            }
+
+            (commit, directory)
+        }
+    };
+
+    let manifest = read_manifest(&directory)?;
+
+    Ok(FetchedSource {
+        directory,
+        resolved: format!("git+{}#{}", url, commit),
+        integrity: String::new(),
+        manifest,
+    })
+}
+
+/// The lock guarding the mirror of the repository at `url`, so that the git fetches of one
+/// install do not run into each other
+fn mirror_lock(url: &str) -> Arc<Mutex<()>> {
+    let mut locks = MIRROR_LOCKS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match locks.iter().find(|(locked_url, _)| locked_url == url) {
+        Some((_, lock)) => lock.clone(),
+        None => {
+            let lock = Arc::new(Mutex::new(()));
+            locks.push((url.to_string(), lock.clone()));
+            lock
+        }
+    }
+}
+
+/// Fetches the latest commits into the mirror of a repository, cloning it first when there is
+/// no mirror yet. New mirrors are cloned into a staging directory that is then renamed into
+/// place, so an interrupted clone never looks complete
+fn update_mirror(url: &str, sources: &Path, mirror: &Path) -> Result<()> {
+    if mirror.join("HEAD").exists() {
+        run_git(
+            url,
+            mirror,
+            &["fetch", "--quiet", "--prune", "--tags", "origin"],
+        )?;
+        return Ok(());
+    }
+
+    let staging = staging_directory(sources);
+    let staging_arg = staging.to_string_lossy().to_string();
+
+    if let Err(error) = run_git(
+        url,
+        sources,
+        &[
+            "clone",
+            "--quiet",
+            "--mirror",
+            "--",
+            url,
+            staging_arg.as_str(),
+        ],
+    ) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+
+    rename_staging(&staging, mirror)
+}
+
+/// The commit `committish` points to in the mirror, or the one of the default branch
+fn find_commit(url: &str, mirror: &Path, committish: Option<&str>) -> Result<String> {
+    let candidate = committish.unwrap_or("HEAD");
+
+    run_git(
+        url,
+        mirror,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", candidate),
+        ],
+    )
+    .map_err(|_| Error::Git {
+        url: url.to_string(),
+        reason: format!(
+            "could not find {}",
+            committish.unwrap_or("the default branch")
+        ),
+    })
+}
+
+/// Exports the files of `commit` into `directory` through a staging directory
+fn export_commit(
+    url: &str,
+    mirror: &Path,
+    commit: &str,
+    sources: &Path,
+    directory: &Path,
+) -> Result<()> {
+    let tarball = git_output(
+        url,
+        mirror,
+        &["archive", "--format=tar.gz", "--prefix=package/", commit],
+    )?;
+
+    let staging = staging_directory(sources);
+
+    if let Err(error) = unpack_tarball(tarball.as_slice(), &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(error);
+    }
+
+    rename_staging(&staging, directory)
+}
+
+fn staging_directory(sources: &Path) -> PathBuf {
+    sources.join(format!(
+        ".staging-{}-{}",
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Moves a complete staging directory into place. When another fetch got there first, its
+/// directory is kept
+fn rename_staging(staging: &Path, directory: &Path) -> Result<()> {
+    if let Err(error) = fs::rename(staging, directory) {
+        let _ = fs::remove_dir_all(staging);
+
+        if !directory.is_dir() {
+            return Err(Error::io(directory)(error));
+        }
+    }
+
+    Ok(())
+}
+
+fn run_git(url: &str, directory: &Path, args: &[&str]) -> Result<String> {
+    let output = git_output(url, directory, args)?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+fn git_output(url: &str, directory: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = match Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+    {
+        Ok(output) => output,
+        Err(error) => {
+            return Err(Error::Command {
+                command: "git".to_string(),
+                source: error,
+            })
+        }
+    };
+
+    if !output.status.success() {
+        return Err(Error::Git {
+            url: url.to_string(),
+            reason: format!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    Ok(output.stdout)
+}
+
+fn read_manifest(directory: &Path) -> Result<Value> {
+    let path = directory.join("package.json");
+
+    let contents = fs::read_to_string(&path).map_err(Error::io(&path))?;
+
+    match serde_json::from_str(&contents) {
+        Ok(manifest) => Ok(manifest),
+        Err(error) => Err(Error::manifest(path.display(), error)),
+    }
+}
+
+/// Unpacks a gzipped package tarball into `destination`, stripping the directory that wraps the
+/// contents of the package (usually `package`). Like npm, only files and directories are
+/// unpacked: symlinks, hardlinks and other entries are skipped, since they could point out of
+/// `destination` and let later entries write through them
+pub fn unpack_tarball<R: Read>(reader: R, destination: &Path) -> Result<()> {
+    unpack_entries(reader, destination).map_err(Error::io(destination))
+}
+
+fn unpack_entries<R: Read>(reader: R, destination: &Path) -> std::io::Result<()> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+
+    fs::create_dir_all(destination)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            continue;
+        }
+
+        let entry_path = entry.path()?.into_owned();
+
+        let relative_path: PathBuf = entry_path.components().skip(1).collect();
+        if relative_path.as_os_str().is_empty()
+            || relative_path
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            continue;
+        }
+
+        let destination = destination.join(relative_path);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        entry.unpack(&destination)?;
+    }
+
+    Ok(())
+}
+
+/// Copies a package directory, leaving out its `node_modules` and `.git` directories
+pub fn copy_package(source: &Path, destination: &Path) -> Result<()> {
+    fs::create_dir_all(destination).map_err(Error::io(destination))?;
+
+    for entry in fs::read_dir(source).map_err(Error::io(source))? {
+        let entry = entry.map_err(Error::io(source))?;
+        let file_name = entry.file_name();
+
+        if file_name == "node_modules" || file_name == ".git" {
+            continue;
+        }
+
+        let destination_path = destination.join(&file_name);
+        let file_type = entry.file_type().map_err(Error::io(entry.path()))?;
+
+        if file_type.is_dir() {
+            copy_package(&entry.path(), &destination_path)?;
+        } else {
+            fs::copy(entry.path(), &destination_path).map_err(Error::io(&destination_path))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the sha512 subresource integrity string of a tarball, as used in npm lockfiles
+pub fn compute_integrity(bytes: &[u8]) -> String {
+    format!("sha512-{}", BASE64.encode(Sha512::digest(bytes)))
+}
+
+/// Checks the tarball of `package` against an integrity string such as `sha512-...`. Only the
+/// strongest supported hash in the string is checked
+pub fn verify_integrity(bytes: &[u8], integrity: &str, package: &str) -> Result<()> {
+    let mut reader = IntegrityReader::new(bytes, integrity, package)?;
+    io::copy(&mut reader, &mut io::sink()).map_err(Error::io(package))?;
+
+    reader.finish().map(drop)
+}
+
+/// Hashes a tarball as it is read, so that it can be checked against its integrity once it was
+/// unpacked, without ever holding the whole tarball in memory
+pub struct IntegrityReader<R> {
+    reader: R,
+    /// The integrity string, which is empty when there is nothing to check
+    integrity: String,
+    package: String,
+    hasher: Hasher,
+    bytes: u64,
+}
+
+enum Hasher {
+    Sha512(Sha512),
+    Sha256(Sha256),
+    Sha1(Sha1),
+}
+
+impl<R: Read> IntegrityReader<R> {
+    /// Fails right away when `integrity` holds none of the supported hashes
+    pub fn new(reader: R, integrity: &str, package: &str) -> Result<IntegrityReader<R>> {
+        let hasher = match expected_hash(integrity) {
+            Some(("sha256", _)) => Hasher::Sha256(Sha256::new()),
+            Some(("sha1", _)) => Hasher::Sha1(Sha1::new()),
+            Some(_) => Hasher::Sha512(Sha512::new()),
+            None if integrity.is_empty() => Hasher::Sha512(Sha512::new()),
+            None => {
+                return Err(Error::Integrity {
+                    package: package.to_string(),
+                    expected: integrity.to_string(),
+                    actual: None,
+                })
+            }
+        };
+
+        Ok(IntegrityReader {
+            reader,
+            integrity: integrity.to_string(),
+            package: package.to_string(),
+            hasher,
+            bytes: 0,
+        })
+    }
+
+    /// Checks what was read against the integrity, returning how many bytes were read
+    pub fn finish(self) -> Result<u64> {
+        let (algorithm, actual) = match self.hasher {
+            Hasher::Sha512(hasher) => ("sha512", BASE64.encode(hasher.finalize())),
+            Hasher::Sha256(hasher) => ("sha256", BASE64.encode(hasher.finalize())),
+            Hasher::Sha1(hasher) => ("sha1", BASE64.encode(hasher.finalize())),
+        };
+
+        match expected_hash(&self.integrity) {
+            Some((_, expected)) if expected != actual => Err(Error::Integrity {
+                package: self.package,
+                expected: format!("{}-{}", algorithm, expected),
+                actual: Some(format!("{}-{}", algorithm, actual)),
+            }),
+            _ => Ok(self.bytes),
+        }
+    }
+}
+
+impl<R: Read> Read for IntegrityReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+
+        match &mut self.hasher {
+            Hasher::Sha512(hasher) => hasher.update(&buf[..read]),
+            Hasher::Sha256(hasher) => hasher.update(&buf[..read]),
+            Hasher::Sha1(hasher) => hasher.update(&buf[..read]),
+        }
+        self.bytes += read as u64;
+
+        Ok(read)
+    }
+}
+
+/// The algorithm and base64 digest of the strongest supported hash in an integrity string
+fn expected_hash(integrity: &str) -> Option<(&'static str, &str)> {
+    ["sha512", "sha256", "sha1"]
+        .into_iter()
+        .find_map(|algorithm| {
+            let prefix = format!("{}-", algorithm);
+
+            integrity
+                .split_whitespace()
+                .find_map(|hash| hash.strip_prefix(&prefix))
+                .map(|hash| (algorithm, hash))
+        })
+}
+
+/// Converts the hex `shasum` found in older registry manifests to an integrity string
+pub fn shasum_to_integrity(shasum: &str) -> Option<String> {
+    if shasum.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes: Option<Vec<u8>> = (0..shasum.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(shasum.get(index..index + 2)?, 16).ok())
+        .collect();
+
+    Some(format!("sha1-{}", BASE64.encode(bytes?)))
+}
+
+pub(crate) fn hex_digest(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
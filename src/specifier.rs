@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use crate::utils::split_package_spec;
+
+/// Where a dependency comes from, as written in the value of a `dependencies` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Specifier {
+    /// A version range or dist-tag served by the registry, e.g. `^1.2.0` or `latest`
+    Registry(String),
+    /// Another registry package installed under the name of the dependency, e.g.
+    /// `npm:string-width@^4.2.0`
+    Alias { name: String, range: String },
+    /// A local directory that gets copied, e.g. `file:../lib`
+    Directory(PathBuf),
+    /// A local directory that gets symlinked, e.g. `link:../lib`
+    Link(PathBuf),
+    /// A tarball on disk, e.g. `file:../lib-1.0.0.tgz`
+    LocalTarball(PathBuf),
+    /// A tarball served over http(s), e.g. `https://example.com/pkg.tgz`
+    RemoteTarball(String),
+    /// A git repository with an optional branch, tag or commit to check out
+    Git {
+        url: String,
+        committish: Option<String>,
+    },
+}
+
+impl Specifier {
+    pub fn parse(spec: &str) -> Specifier {
+        let spec = spec.trim();
+
+        if let Some(aliased) = spec.strip_prefix("npm:") {
+            let (name, range) = split_package_spec(aliased);
+
+            return Specifier::Alias {
+                name: name.to_string(),
+                range: range.unwrap_or("latest").to_string(),
+            };
+        }
+
+        if let Some(path) = spec.strip_prefix("link:") {
+            return Specifier::Link(PathBuf::from(path));
+        }
+
+        if let Some(path) = spec.strip_prefix("file:") {
+            return local_specifier(path);
+        }
+
+        if spec.starts_with("./")
+            || spec.starts_with("../")
+            || spec.starts_with('/')
+            || spec.starts_with("~/")
+        {
+            return local_specifier(spec);
+        }
+
+        if let Some(git) = parse_git_specifier(spec) {
+            return git;
+        }
+
+        if spec.starts_with("http://") || spec.starts_with("https://") {
+            return Specifier::RemoteTarball(spec.to_string());
+        }
+
+        Specifier::Registry(spec.to_string())
+    }
+
+    pub fn is_registry(&self) -> bool {
+        matches!(self, Specifier::Registry(_))
+    }
+
+    /// The registry package and range to resolve for a dependency called `name`, taking aliases
+    /// into account. Returns `None` for directories, tarballs and git repositories
+    pub fn registry_package(&self, name: &str) -> Option<(String, String)> {
+        match self {
+            Specifier::Registry(range) => Some((name.to_string(), range.clone())),
+            Specifier::Alias { name, range } => Some((name.clone(), range.clone())),
+            _ => None,
+        }
+    }
+}
+
+fn local_specifier(path: &str) -> Specifier {
+    let path = match path.strip_prefix("~/") {
+        Some(relative) => match dirs::home_dir() {
+            Some(home) => home.join(relative),
+            None => PathBuf::from(path),
+        },
+        None => PathBuf::from(path),
+    };
+
+    let is_tarball = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .map_or(false, |name| {
+            name.ends_with(".tgz") || name.ends_with(".tar.gz") || name.ends_with(".tar")
+        });
+
+    if is_tarball {
+        Specifier::LocalTarball(path)
+    } else {
+        Specifier::Directory(path)
+    }
+}
+
+fn parse_git_specifier(spec: &str) -> Option<Specifier> {
+    let (location, committish) = match spec.split_once('#') {
+        Some((location, committish)) if !committish.is_empty() => {
+            (location, Some(committish.to_string()))
+        }
+        Some((location, _)) => (location, None),
+        None => (spec, None),
+    };
+
+    let hosts = [
+        ("github:", "https://github.com/"),
+        ("gitlab:", "https://gitlab.com/"),
+        ("bitbucket:", "https://bitbucket.org/"),
+    ];
+
+    for (prefix, host) in hosts {
+        if let Some(repository) = location.strip_prefix(prefix) {
+            return Some(Specifier::Git {
+                url: format!("{}{}.git", host, repository.trim_end_matches(".git")),
+                committish,
+            });
+        }
+    }
+
+    // a url starting with `-` would be taken as an option by git
+    if let Some(url) = location
+        .strip_prefix("git+")
+        .filter(|url| !url.starts_with('-'))
+    {
+        return Some(Specifier::Git {
+            url: url.to_string(),
+            committish,
+        });
+    }
+
+    if location.starts_with("git://") || (location.ends_with(".git") && location.contains("://")) {
+        return Some(Specifier::Git {
+            url: location.to_string(),
+            committish,
+        });
+    }
+
+    // `user/repo` is a shorthand for a GitHub repository
+    let is_github_shorthand = !location.contains(':')
+        && !location.starts_with('@')
+        && location.split('/').count() == 2
+        && location.split('/').all(|part| !part.is_empty());
+
+    if is_github_shorthand {
+        return Some(Specifier::Git {
+            url: format!("https://github.com/{}.git", location),
+            committish,
+        });
+    }
+
+    None
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::{Error, Result};
+use crate::lockfile::LockedPackage;
+use crate::sources::{hex_digest, unpack_tarball, IntegrityReader};
+
+/// Tells apart the staging directories of packages unpacked at the same time
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The cache that fetched registry packages are unpacked into, shared between projects. Each
+/// package lives in a directory named after its integrity hash
+#[derive(Debug, Clone)]
+pub struct Store {
+    directory: PathBuf,
+}
+
+impl Store {
+    pub fn new(directory: impl Into<PathBuf>) -> Store {
+        Store {
+            directory: directory.into(),
+        }
+    }
+
+    /// The store inside the blaze cache directory `cache_directory`
+    pub fn in_cache(cache_directory: &Path) -> Store {
+        Store::new(cache_directory.join("store"))
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Where the contents of `package` are kept, whether or not it was fetched yet
+    pub fn package_directory(&self, package: &LockedPackage) -> PathBuf {
+        let key = if package.integrity.is_empty() {
+            format!("{}@{}", package.name, package.version)
+        } else {
+            package.integrity.clone()
+        };
+
+        self.directory.join(hex_digest(key.as_bytes()))
+    }
+
+    pub fn contains(&self, package: &LockedPackage) -> bool {
+        self.package_directory(package).is_dir()
+    }
+
+    /// Unpacks the tarball of `package` into the store as it is read, checking it against the
+    /// integrity of the package, and returns where it was unpacked along with the size of the
+    /// tarball. The tarball is unpacked into a staging directory that is only renamed into place
+    /// once it matches, so an interrupted unpack or a tampered tarball never looks complete
+    pub fn add<R: Read>(&self, package: &LockedPackage, tarball: R) -> Result<(PathBuf, u64)> {
+        let directory = self.package_directory(package);
+
+        fs::create_dir_all(&self.directory).map_err(Error::io(&self.directory))?;
+
+        let staging = self.directory.join(format!(
+            ".staging-{}-{}",
+            std::process::id(),
+            STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let label = format!("{}@{}", package.name, package.version);
+        let mut tarball = IntegrityReader::new(tarball, &package.integrity, &label)?;
+
+        // tar stops reading at the end of the archive, but the rest of the tarball is hashed too
+        let unpacked = unpack_tarball(&mut tarball, &staging).and_then(|()| {
+            io::copy(&mut tarball, &mut io::sink())
+                .map(drop)
+                .map_err(Error::io(&staging))
+        });
+
+        let bytes = match unpacked.and_then(|()| tarball.finish()) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(error);
+            }
+        };
+
+        if let Err(error) = fs::rename(&staging, &directory) {
+            let _ = fs::remove_dir_all(&staging);
+
+            // another install may have added the same package in the meantime
+            if !directory.is_dir() {
+                return Err(Error::io(&directory)(error));
+            }
+        }
+
+        Ok((directory, bytes))
+    }
+}
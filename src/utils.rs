@@ -1,33 +1,263 @@
-use std::{
-    fs::{self, File},
-    io::Read,
-    path::Path,
-};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::task::JoinError;
 
-pub fn copy_folder_contents(source: &Path, destination: &Path) -> std::io::Result<()> {
-    for entry in fs::read_dir(source)? {
-        let entry = entry?;
+use crate::error::{Error, Result};
+
+pub fn copy_folder_contents(source: &Path, destination: &Path) -> Result<()> {
+    for entry in fs::read_dir(source).map_err(Error::io(source))? {
+        let entry = entry.map_err(Error::io(source))?;
 
-        let file_type = entry.file_type()?;
         let entry_path = entry.path();
-        let destination_path = destination.join(entry_path.file_name().unwrap());
+        let file_type = entry.file_type().map_err(Error::io(&entry_path))?;
+        let destination_path = destination.join(entry.file_name());
 
         if file_type.is_dir() {
-            fs::create_dir(&destination_path)?;
+            fs::create_dir(&destination_path).map_err(Error::io(&destination_path))?;
             copy_folder_contents(&entry_path, &destination_path)?;
         } else {
-            fs::copy(&entry_path, &destination_path)?;
+            fs::copy(&entry_path, &destination_path).map_err(Error::io(&destination_path))?;
         }
     }
 
     Ok(())
 }
 
-pub fn read_package_json() -> Result<String, std::io::Error> {
-    let mut file = File::open("package.json")?;
+/// The blaze directory inside the cache directory of the platform, which holds the store, the
+/// fetched sources and the packages installed by `exec` unless another one is configured
+pub fn default_cache_directory() -> Result<PathBuf> {
+    match dirs::cache_dir() {
+        Some(cache_dir) => Ok(cache_dir.join(env!("CARGO_PKG_NAME"))),
+        None => Err(Error::NoCacheDirectory),
+    }
+}
+
+/// Reads the `package.json` in `directory`
+pub fn read_package_json(directory: &Path) -> Result<String> {
+    let path = directory.join("package.json");
+    fs::read_to_string(&path).map_err(Error::io(&path))
+}
+
+/// The formatting of a JSON file, kept when the file is written back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonStyle {
+    pub indent: String,
+    pub newline: &'static str,
+    pub trailing_newline: bool,
+}
+
+impl Default for JsonStyle {
+    /// The style npm writes `package.json` with
+    fn default() -> JsonStyle {
+        JsonStyle {
+            indent: "  ".to_string(),
+            newline: "\n",
+            trailing_newline: true,
+        }
+    }
+}
+
+impl JsonStyle {
+    /// Detects the indentation (from the first indented line), the line endings and whether
+    /// `contents` ends with a newline
+    pub fn detect(contents: &str) -> JsonStyle {
+        let indent = contents
+            .lines()
+            .map(|line| {
+                let content_start = line.len() - line.trim_start_matches([' ', '\t']).len();
+                &line[..content_start]
+            })
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+
+        JsonStyle {
+            indent: indent.to_string(),
+            newline: if contents.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+            trailing_newline: contents.ends_with('\n'),
+        }
+    }
+
+    pub fn to_string<T: serde::Serialize>(&self, value: &T) -> serde_json::Result<String> {
+        let mut buffer = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+
+        value.serialize(&mut serializer)?;
+
+        // serde_json only writes valid UTF-8
+        let mut json = String::from_utf8_lossy(&buffer).replace('\n', self.newline);
+        if self.trailing_newline {
+            json.push_str(self.newline);
+        }
+
+        Ok(json)
+    }
+}
+
+pub fn read_installed_package_json(
+    node_modules: &Path,
+    package_name: &str,
+) -> Option<serde_json::Value> {
+    let path = node_modules.join(package_name).join("package.json");
+    let contents = fs::read_to_string(path).ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn read_installed_version(node_modules: &Path, package_name: &str) -> Option<String> {
+    let package_json = read_installed_package_json(node_modules, package_name)?;
+
+    package_json["version"]
+        .as_str()
+        .map(|version| version.to_string())
+}
+
+/// Splits `name@version` into its parts, taking care of scoped names such as `@types/node@18`
+pub fn split_package_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.rfind('@') {
+        Some(index) if index > 0 => (&spec[..index], Some(&spec[index + 1..])),
+        _ => (spec, None),
+    }
+}
 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
+/// Checks that `name` is a package name: a single file name, optionally under one `@scope/`.
+/// Names are joined onto `node_modules`, so anything that could lead out of it, or onto
+/// blaze's own entries in it such as `.bin`, is rejected
+pub fn validate_package_name(name: &str) -> Result<()> {
+    let segments: Vec<&str> = name.split('/').collect();
 
-    Ok(contents)
+    let is_valid_segment = |segment: &str| {
+        !segment.is_empty() && !segment.starts_with('.') && !segment.contains(['\\', ':'])
+    };
+
+    let is_valid = match segments.as_slice() {
+        [name] => is_valid_segment(name) && !name.starts_with('@'),
+        [scope, name] => scope.strip_prefix('@').map_or(false, |scope| {
+            is_valid_segment(scope) && is_valid_segment(name)
+        }),
+        _ => false,
+    };
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidName {
+            name: name.to_string(),
+        })
+    }
+}
+
+/// The total size of the files in `directory`, without following symlinks. Entries that
+/// cannot be read count as empty
+pub fn directory_size(directory: &Path) -> u64 {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.path().symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Lists the names of the packages extracted into `node_modules`, including scoped packages
+pub fn installed_package_names(node_modules: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+
+    let entries = match fs::read_dir(node_modules) {
+        Ok(entries) => entries,
+        Err(_) => return names,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || !entry.path().is_dir() {
+            continue;
+        }
+
+        if name.starts_with('@') {
+            if let Ok(scoped_entries) = fs::read_dir(entry.path()) {
+                for scoped_entry in scoped_entries.flatten() {
+                    if scoped_entry.path().is_dir() {
+                        names.push(format!(
+                            "{}/{}",
+                            name,
+                            scoped_entry.file_name().to_string_lossy()
+                        ));
+                    }
+                }
+            }
+        } else {
+            names.push(name);
+        }
+    }
+
+    names.sort();
+    names
+}
+
+/// Unwraps the result of a spawned task, resuming the panic of a task that panicked
+pub(crate) fn joined<T>(result: std::result::Result<T, JoinError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_json_back_in_the_style_it_was_read_in() {
+        for indent in ["  ", "    ", "\t"] {
+            for newline in ["\n", "\r\n"] {
+                for trailing_newline in [true, false] {
+                    let mut contents = [
+                        "{",
+                        &format!("{}\"name\": \"project\",", indent),
+                        &format!("{}\"dependencies\": {{", indent),
+                        &format!("{}{}\"lodash\": \"^4.17.21\"", indent, indent),
+                        &format!("{}}}", indent),
+                        "}",
+                    ]
+                    .join(newline);
+                    if trailing_newline {
+                        contents.push_str(newline);
+                    }
+
+                    let style = JsonStyle::detect(&contents);
+                    assert_eq!(style.indent, indent);
+                    assert_eq!(style.newline, newline);
+                    assert_eq!(style.trailing_newline, trailing_newline);
+
+                    let value: serde_json::Value = serde_json::from_str(&contents).unwrap();
+                    assert_eq!(style.to_string(&value).unwrap(), contents);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn defaults_to_the_style_of_npm() {
+        let style = JsonStyle::detect("{}");
+        assert_eq!(style.indent, "  ");
+        assert!(!style.trailing_newline);
+
+        let value = serde_json::json!({ "name": "project" });
+        assert_eq!(
+            JsonStyle::default().to_string(&value).unwrap(),
+            "{\n  \"name\": \"project\"\n}\n"
+        );
+    }
 }
@@ -0,0 +1,202 @@
+use semver::{Version, VersionReq};
+
+use crate::error::{Error, Result};
+
+/// An npm style version range such as `^1.2.0`, `>=1.0.0 <2.0.0` or `1.x || 2.x`
+#[derive(Debug, Clone)]
+pub struct VersionRange {
+    comparator_sets: Vec<VersionReq>,
+}
+
+impl VersionRange {
+    pub fn parse(range: &str) -> Result<VersionRange> {
+        let mut comparator_sets = Vec::new();
+
+        for set in range.split("||") {
+            let converted = convert_comparator_set(set.trim());
+
+            match VersionReq::parse(&converted) {
+                Ok(req) => comparator_sets.push(req),
+                Err(error) => {
+                    return Err(Error::InvalidSpec {
+                        spec: range.to_string(),
+                        reason: error.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(VersionRange { comparator_sets })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparator_sets.iter().any(|req| req.matches(version))
+    }
+
+    /// Whether some version satisfies both ranges. Only the versions at the bounds of the two
+    /// ranges are tried, which is enough to find a common version when there is one, since every
+    /// comparator set is an interval
+    pub fn intersects(&self, other: &VersionRange) -> bool {
+        self.bounds()
+            .chain(other.bounds())
+            .any(|version| self.matches(&version) && other.matches(&version))
+    }
+
+    /// The versions named by the comparators of the range, along with the next patch, minor and
+    /// major version after each of them, which are the lowest versions strict bounds allow
+    fn bounds(&self) -> impl Iterator<Item = Version> + '_ {
+        let comparators = self
+            .comparator_sets
+            .iter()
+            .flat_map(|req| req.comparators.iter());
+
+        comparators
+            .flat_map(|comparator| {
+                let (major, minor, patch) = (
+                    comparator.major,
+                    comparator.minor.unwrap_or(0),
+                    comparator.patch.unwrap_or(0),
+                );
+                let mut version = Version::new(major, minor, patch);
+                version.pre = comparator.pre.clone();
+
+                [
+                    version,
+                    Version::new(major, minor, patch + 1),
+                    Version::new(major, minor + 1, 0),
+                    Version::new(major + 1, 0, 0),
+                ]
+            })
+            .chain(std::iter::once(Version::new(0, 0, 0)))
+    }
+
+    pub fn max_satisfying<'a, I>(&self, versions: I) -> Option<&'a Version>
+    where
+        I: IntoIterator<Item = &'a Version>,
+    {
+        versions
+            .into_iter()
+            .filter(|version| self.matches(version))
+            .max()
+    }
+}
+
+pub fn satisfies(version: &str, range: &str) -> bool {
+    match (Version::parse(version), VersionRange::parse(range)) {
+        (Ok(version), Ok(range)) => range.matches(&version),
+        _ => false,
+    }
+}
+
+/// Converts a single npm comparator set into the syntax understood by `semver::VersionReq`
+fn convert_comparator_set(set: &str) -> String {
+    if set.is_empty() {
+        return "*".to_string();
+    }
+
+    // hyphen ranges: `1.2.3 - 2.3.4` => `>=1.2.3, <=2.3.4`
+    if let Some((low, high)) = set.split_once(" - ") {
+        return format!(">={}, <={}", strip_v(low.trim()), strip_v(high.trim()));
+    }
+
+    let mut comparators = Vec::new();
+    let mut pending_operator = String::new();
+
+    for token in set.split_whitespace() {
+        let operator_len = token
+            .find(|c: char| !matches!(c, '<' | '>' | '=' | '^' | '~'))
+            .unwrap_or(token.len());
+        let (operator, version) = token.split_at(operator_len);
+
+        if version.is_empty() {
+            // the operator was separated from its version, e.g. `>= 1.2.3`
+            pending_operator.push_str(operator);
+            continue;
+        }
+
+        let operator = format!("{}{}", pending_operator, operator);
+        pending_operator.clear();
+
+        let version = strip_v(version);
+
+        if matches!(version, "*" | "x" | "X") {
+            comparators.push("*".to_string());
+        } else if operator.is_empty() {
+            // npm treats a bare version as an exact match
+            comparators.push(format!("={}", version));
+        } else {
+            comparators.push(format!("{}{}", operator, version));
+        }
+    }
+
+    if comparators.is_empty() {
+        return "*".to_string();
+    }
+
+    comparators.join(", ")
+}
+
+fn strip_v(version: &str) -> &str {
+    version.trim_start_matches(['v', 'V'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_npm_ranges() {
+        // (range, matching versions, versions that do not match)
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("1.2.3", &["1.2.3", "v1.2.3"], &["1.2.4", "1.2.2"]),
+            ("=1.2.3", &["1.2.3"], &["1.2.4"]),
+            ("v1.2.3", &["1.2.3"], &["1.3.0"]),
+            ("^1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0"]),
+            ("^0.2.3", &["0.2.3", "0.2.9"], &["0.3.0"]),
+            ("~1.2.3", &["1.2.3", "1.2.9"], &["1.3.0"]),
+            (">=1.2.0 <2.0.0", &["1.2.0", "1.99.0"], &["1.1.9", "2.0.0"]),
+            (">= 1.2.0", &["1.2.0", "3.0.0"], &["1.1.0"]),
+            ("1.2.3 - 2.3.4", &["1.2.3", "2.3.4"], &["1.2.2", "2.3.5"]),
+            ("1.x", &["1.0.0", "1.9.9"], &["2.0.0", "0.9.0"]),
+            ("1.2.x", &["1.2.0", "1.2.9"], &["1.3.0"]),
+            ("*", &["0.0.1", "9.9.9"], &["1.0.0-beta.1"]),
+            ("x", &["1.0.0"], &[]),
+            ("", &["1.0.0"], &[]),
+            ("1.x || >=2.5.0", &["1.5.0", "2.5.0", "3.0.0"], &["2.4.0"]),
+            ("^1.0.0 || ^3.0.0", &["1.1.0", "3.1.0"], &["2.0.0"]),
+            (
+                "^1.2.3-beta.2",
+                &["1.2.3-beta.2", "1.2.3-beta.4", "1.2.3"],
+                &["1.2.3-alpha.9", "1.2.4-beta.1"],
+            ),
+            ("1.0.0-rc.1", &["1.0.0-rc.1"], &["1.0.0", "1.0.0-rc.2"]),
+            (">1.0.0", &["1.0.1"], &["1.0.0", "1.1.0-rc.1"]),
+        ];
+
+        for (range, matching, not_matching) in cases {
+            let parsed = VersionRange::parse(range).unwrap();
+
+            for version in *matching {
+                let version = Version::parse(strip_v(version)).unwrap();
+                assert!(parsed.matches(&version), "`{}` matches {}", range, version);
+            }
+            for version in *not_matching {
+                let version = Version::parse(version).unwrap();
+                assert!(!parsed.matches(&version), "`{}` rejects {}", range, version);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for range in ["latest", "^a.b.c", "1.2.3.4"] {
+            assert!(
+                VersionRange::parse(range).is_err(),
+                "`{}` is invalid",
+                range
+            );
+        }
+        assert!(!satisfies("1.0.0", "latest"));
+        assert!(!satisfies("not-a-version", "*"));
+    }
+}
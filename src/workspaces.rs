@@ -0,0 +1,252 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dependencies::Dependencies;
+use crate::error::{Error, Result};
+use crate::utils::{read_package_json, validate_package_name};
+
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub name: String,
+    pub version: String,
+    /// The directory of the workspace, relative to the project root
+    pub path: PathBuf,
+    /// The directory of the workspace, joined onto the project root
+    pub directory: PathBuf,
+}
+
+impl Workspace {
+    pub fn package_json_path(&self) -> PathBuf {
+        self.directory.join("package.json")
+    }
+
+    pub fn dependencies(&self) -> Result<Dependencies> {
+        Dependencies::from_package_json_at(&self.package_json_path())
+    }
+}
+
+/// Finds the workspaces declared in the `workspaces` field of the `package.json` in `root`, which
+/// is either an array of glob patterns or an object with a `packages` array. Patterns starting
+/// with `!` exclude directories matched by earlier patterns
+pub fn find_workspaces(root: &Path) -> Result<Vec<Workspace>> {
+    let contents = read_package_json(root)?;
+
+    let package_json: Value = match serde_json::from_str(&contents) {
+        Ok(package_json) => package_json,
+        Err(error) => return Err(Error::manifest(root.join("package.json").display(), error)),
+    };
+
+    let patterns = match &package_json["workspaces"] {
+        Value::Array(patterns) => patterns.clone(),
+        Value::Object(workspaces) => match workspaces.get("packages") {
+            Some(Value::Array(patterns)) => patterns.clone(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    let mut directories: Vec<PathBuf> = Vec::new();
+
+    for pattern in patterns.iter().filter_map(Value::as_str) {
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+
+        if let Some(excluded) = pattern.strip_prefix('!') {
+            directories.retain(|directory| !glob_matches(excluded, &path_to_pattern(directory)));
+            continue;
+        }
+
+        for directory in expand_pattern(root, pattern) {
+            if !directories.contains(&directory) {
+                directories.push(directory);
+            }
+        }
+    }
+
+    let mut workspaces = Vec::new();
+
+    for path in directories {
+        let directory = root.join(&path);
+        let contents = match read_package_json(&directory) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+
+        let package_json: Value = match serde_json::from_str(&contents) {
+            Ok(package_json) => package_json,
+            Err(error) => {
+                return Err(Error::manifest(
+                    directory.join("package.json").display(),
+                    error,
+                ))
+            }
+        };
+
+        let name = match package_json["name"].as_str() {
+            Some(name) => name.to_string(),
+            None => {
+                return Err(Error::Workspace {
+                    reason: format!("The workspace in {} does not have a name", path.display()),
+                })
+            }
+        };
+
+        validate_package_name(&name)?;
+
+        if workspaces
+            .iter()
+            .any(|workspace: &Workspace| workspace.name == name)
+        {
+            return Err(Error::Workspace {
+                reason: format!("Found more than one workspace named {}", name),
+            });
+        }
+
+        workspaces.push(Workspace {
+            name,
+            version: package_json["version"]
+                .as_str()
+                .unwrap_or("0.0.0")
+                .to_string(),
+            path,
+            directory,
+        });
+    }
+
+    workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(workspaces)
+}
+
+/// Keeps the workspaces whose name or path matches one of `filters`. Filters may contain `*`
+/// wildcards, and every filter has to match at least one workspace
+pub fn filter_workspaces(workspaces: &[Workspace], filters: &[String]) -> Result<Vec<Workspace>> {
+    let mut selected: Vec<Workspace> = Vec::new();
+
+    for filter in filters {
+        let filter = filter.trim_start_matches("./").trim_end_matches('/');
+        let mut matched = false;
+
+        for workspace in workspaces {
+            if glob_matches(filter, &workspace.name)
+                || glob_matches(filter, &path_to_pattern(&workspace.path))
+            {
+                matched = true;
+
+                if !selected.iter().any(|other| other.name == workspace.name) {
+                    selected.push(workspace.clone());
+                }
+            }
+        }
+
+        if !matched {
+            return Err(Error::Workspace {
+                reason: format!("No workspace matches `{}`", filter),
+            });
+        }
+    }
+
+    Ok(selected)
+}
+
+fn path_to_pattern(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Expands a pattern such as `packages/*` or `apps/**` into the directories it matches, relative
+/// to `root`
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let mut matches = Vec::new();
+
+    expand_segments(root, Path::new(""), &segments, &mut matches);
+
+    matches.sort();
+    matches
+}
+
+fn expand_segments(root: &Path, directory: &Path, segments: &[&str], matches: &mut Vec<PathBuf>) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            if root.join(directory).join("package.json").exists() {
+                matches.push(directory.to_path_buf());
+            }
+            return;
+        }
+    };
+
+    if *segment == "**" {
+        // `**` matches this directory as well as any of its descendants
+        expand_segments(root, directory, rest, matches);
+
+        for child in child_directories(root, directory) {
+            expand_segments(root, &child, segments, matches);
+        }
+    } else if segment.contains('*') {
+        for child in child_directories(root, directory) {
+            let name = child
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            if glob_matches(segment, &name) {
+                expand_segments(root, &child, rest, matches);
+            }
+        }
+    } else {
+        let child = directory.join(segment);
+        if root.join(&child).is_dir() {
+            expand_segments(root, &child, rest, matches);
+        }
+    }
+}
+
+fn child_directories(root: &Path, directory: &Path) -> Vec<PathBuf> {
+    let mut children: Vec<PathBuf> = match fs::read_dir(root.join(directory)) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name())
+            .filter(|name| {
+                let name = name.to_string_lossy();
+                name != "node_modules" && !name.starts_with('.')
+            })
+            .map(|name| directory.join(name))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    children.sort();
+    children
+}
+
+/// Matches `text` against a pattern where `*` stands for any sequence of characters
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
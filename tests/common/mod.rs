@@ -0,0 +1,215 @@
+// every test binary uses a different part of the helpers
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+use blaze::registry::{LocalRegistry, MemoryRegistry};
+use blaze::reporter::{Event, Reporter};
+use blaze::sources::compute_integrity;
+
+/// Builds a gzipped package tarball holding `manifest` as its `package.json` along with
+/// `files`, wrapped in a `package` directory like the tarballs of the npm registry
+pub fn package_tarball(manifest: &Value, files: &[(&str, &str)]) -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest = manifest.to_string();
+    let entries = std::iter::once(("package.json", manifest.as_str())).chain(files.iter().copied());
+
+    for (path, contents) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        builder
+            .append_data(
+                &mut header,
+                Path::new("package").join(path),
+                contents.as_bytes(),
+            )
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// A package to publish to a `MemoryRegistry`: its name, version and dependencies
+pub struct Package<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub dependencies: &'a [(&'a str, &'a str)],
+}
+
+/// A registry serving `packages`, with every version published under its own tarball and the
+/// newest version of every package tagged as `latest`
+pub fn memory_registry(packages: &[Package]) -> MemoryRegistry {
+    let (packuments, tarballs) = publish(packages);
+    let mut registry = MemoryRegistry::new();
+
+    for packument in packuments {
+        registry.add_packument(packument);
+    }
+    for (name, version, tarball) in tarballs {
+        registry.add_tarball(&name, &version, tarball);
+    }
+
+    registry
+}
+
+/// Like `memory_registry`, but with the packuments and tarballs written to `directory` in the
+/// layout `LocalRegistry` reads
+pub fn local_registry(directory: &Path, packages: &[Package]) -> LocalRegistry {
+    let (packuments, tarballs) = publish(packages);
+
+    for packument in packuments {
+        let name = packument["name"].as_str().unwrap();
+        write(
+            directory.join(name).join("index.json"),
+            packument.to_string(),
+        );
+    }
+    for (name, version, tarball) in tarballs {
+        let unscoped_name = name.rsplit('/').next().unwrap();
+        write(
+            directory
+                .join(&name)
+                .join("-")
+                .join(format!("{}-{}.tgz", unscoped_name, version)),
+            tarball,
+        );
+    }
+
+    LocalRegistry::new(directory)
+}
+
+/// The tarball of a published version: its package name, version and contents
+type Tarball = (String, String, Vec<u8>);
+
+/// Builds the packuments of `packages` along with the tarball of every version
+fn publish(packages: &[Package]) -> (Vec<Value>, Vec<Tarball>) {
+    let mut packuments: Vec<Value> = Vec::new();
+    let mut tarballs = Vec::new();
+
+    for package in packages {
+        let dependencies: serde_json::Map<String, Value> = package
+            .dependencies
+            .iter()
+            .map(|(name, range)| (name.to_string(), json!(range)))
+            .collect();
+
+        let manifest = json!({
+            "name": package.name,
+            "version": package.version,
+            "dependencies": dependencies,
+        });
+        let tarball = package_tarball(&manifest, &[("index.js", "module.exports = {}\n")]);
+
+        let mut version = manifest.clone();
+        version["dist"] = json!({
+            "tarball": format!("https://registry.test/{}/-/{}.tgz", package.name, package.version),
+            "integrity": compute_integrity(&tarball),
+        });
+
+        tarballs.push((
+            package.name.to_string(),
+            package.version.to_string(),
+            tarball,
+        ));
+
+        match packuments
+            .iter_mut()
+            .find(|packument| packument["name"] == package.name)
+        {
+            Some(packument) => {
+                packument["versions"][package.version] = version;
+
+                let latest =
+                    semver::Version::parse(packument["dist-tags"]["latest"].as_str().unwrap())
+                        .unwrap();
+                if semver::Version::parse(package.version).unwrap() > latest {
+                    packument["dist-tags"]["latest"] = json!(package.version);
+                }
+            }
+            None => packuments.push(json!({
+                "name": package.name,
+                "dist-tags": { "latest": package.version },
+                "versions": { package.version: version },
+            })),
+        }
+    }
+
+    (packuments, tarballs)
+}
+
+/// Writes `contents` to `path`, creating its parent directories
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+    let path = path.as_ref();
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+/// Whether the `git` binary is available, since the git tests cannot run without it
+pub fn has_git() -> bool {
+    Command::new("git").arg("--version").output().is_ok()
+}
+
+/// Runs git in `directory` with a fixed identity, returning its trimmed output
+pub fn git(directory: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=blaze",
+            "-c",
+            "user.email=blaze@example.com",
+        ])
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// The url of a file path, as git expects it
+pub fn file_url(path: &Path) -> String {
+    url::Url::from_file_path(path).unwrap().to_string()
+}
+
+/// A reporter keeping every event it receives
+#[derive(Default)]
+pub struct RecordingReporter {
+    events: Mutex<Vec<Event>>,
+}
+
+impl RecordingReporter {
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Warning { message } => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Reporter for RecordingReporter {
+    fn report(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+}
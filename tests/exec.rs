@@ -0,0 +1,81 @@
+mod common;
+
+use serde_json::json;
+use std::sync::Arc;
+
+use blaze::dependencies::Dependencies;
+use blaze::exec::exec;
+use blaze::registry::MemoryRegistry;
+use blaze::reporter::SilentReporter;
+use blaze::sources::compute_integrity;
+
+use common::{memory_registry, package_tarball, Package};
+
+/// A registry serving `tool`, whose binary exits with code 3, and its dependency `helper` unless
+/// `with_helper` is false
+fn registry(with_helper: bool) -> MemoryRegistry {
+    let helper = [Package {
+        name: "helper",
+        version: "1.0.0",
+        dependencies: &[],
+    }];
+    let mut registry = memory_registry(if with_helper { &helper } else { &[] });
+
+    let manifest = json!({
+        "name": "tool",
+        "version": "1.0.0",
+        "bin": "cli.sh",
+        "dependencies": { "helper": "^1.0.0" },
+    });
+    let tarball = package_tarball(&manifest, &[("cli.sh", "#!/bin/sh\nexit 3\n")]);
+
+    let mut version = manifest;
+    version["dist"] = json!({
+        "tarball": "https://registry.test/tool/-/tool-1.0.0.tgz",
+        "integrity": compute_integrity(&tarball),
+    });
+    registry.add_packument(json!({
+        "name": "tool",
+        "dist-tags": { "latest": "1.0.0" },
+        "versions": { "1.0.0": version },
+    }));
+    registry.add_tarball("tool", "1.0.0", tarball);
+
+    registry
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn only_reuses_complete_installs_of_the_exec_cache() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+
+    let run = |registry: MemoryRegistry| {
+        let root = root.path().to_path_buf();
+        let installer = Dependencies::default()
+            .with_registry(Arc::new(registry))
+            .with_cache_directory(cache.path());
+        async move {
+            exec(
+                &root,
+                installer,
+                Arc::new(SilentReporter),
+                "tool",
+                None,
+                &[],
+            )
+            .await
+        }
+    };
+
+    // `helper` is missing, so the install fails and leaves nothing behind to be taken as complete
+    assert!(run(registry(false)).await.is_err());
+    let exec_cache = cache.path().join("exec");
+    let entries: Vec<_> = std::fs::read_dir(&exec_cache)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+        .unwrap_or_default();
+    assert!(entries.is_empty(), "left {:?} behind", entries);
+
+    assert_eq!(run(registry(true)).await.unwrap(), 3);
+    assert_eq!(run(registry(true)).await.unwrap(), 3);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use blaze::export::{package_lock, write_package_lock, PACKAGE_LOCK_NAME};
+use blaze::lockfile::{LockedPackage, Lockfile};
+
+use common::write;
+
+fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn locked(
+    name: &str,
+    version: &str,
+    integrity: &str,
+    dependencies: &[(&str, &str)],
+) -> LockedPackage {
+    LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        resolved: format!("https://registry.test/{0}/-/{0}-{1}.tgz", name, version),
+        integrity: integrity.to_string(),
+        dependencies: map(dependencies),
+    }
+}
+
+/// A project depending on a registry package, an alias, a local directory and a workspace, with
+/// a dev dependency that nothing else needs
+fn lockfile() -> Lockfile {
+    let mut packages = BTreeMap::new();
+    packages.insert(
+        "a".to_string(),
+        locked("a", "1.0.0", "sha512-a", &[("shared", "^1.0.0")]),
+    );
+    packages.insert(
+        "shared".to_string(),
+        locked("shared", "1.1.0", "sha512-shared", &[]),
+    );
+    packages.insert("dev-tool".to_string(), locked("dev-tool", "2.0.0", "", &[]));
+    packages.insert(
+        "str".to_string(),
+        locked("string-width", "4.2.3", "sha512-string-width", &[]),
+    );
+    packages.insert(
+        "lib".to_string(),
+        LockedPackage {
+            name: "lib".to_string(),
+            version: "0.1.0".to_string(),
+            resolved: "file:lib".to_string(),
+            ..LockedPackage::default()
+        },
+    );
+
+    Lockfile {
+        dependencies: map(&[
+            ("a", "^1.0.0"),
+            ("lib", "file:lib"),
+            ("str", "npm:string-width@^4.0.0"),
+        ]),
+        dev_dependencies: map(&[("dev-tool", "^2.0.0")]),
+        workspaces: map(&[("web", "packages/web")]),
+        overrides: BTreeMap::new(),
+        packages,
+    }
+}
+
+fn package_json() -> Value {
+    json!({
+        "name": "project",
+        "version": "1.0.0",
+        "private": true,
+        "workspaces": ["packages/*"],
+        "dependencies": {
+            "a": "^1.0.0",
+            "lib": "file:lib",
+            "str": "npm:string-width@^4.0.0",
+        },
+        "devDependencies": { "dev-tool": "^2.0.0" },
+    })
+}
+
+#[test]
+fn exports_a_package_lock() {
+    let root = tempfile::tempdir().unwrap();
+    write(
+        root.path().join("packages/web/package.json"),
+        json!({ "name": "web", "version": "1.0.0", "private": true }).to_string(),
+    );
+
+    let package_lock = package_lock(&lockfile(), root.path(), &package_json());
+
+    assert_eq!(
+        package_lock,
+        json!({
+            "name": "project",
+            "version": "1.0.0",
+            "lockfileVersion": 3,
+            "requires": true,
+            "packages": {
+                "": {
+                    "name": "project",
+                    "version": "1.0.0",
+                    "workspaces": ["packages/*"],
+                    "dependencies": {
+                        "a": "^1.0.0",
+                        "lib": "file:lib",
+                        "str": "npm:string-width@^4.0.0",
+                    },
+                    "devDependencies": { "dev-tool": "^2.0.0" },
+                },
+                "node_modules/a": {
+                    "version": "1.0.0",
+                    "resolved": "https://registry.test/a/-/a-1.0.0.tgz",
+                    "integrity": "sha512-a",
+                    "dependencies": { "shared": "^1.0.0" },
+                },
+                "node_modules/shared": {
+                    "version": "1.1.0",
+                    "resolved": "https://registry.test/shared/-/shared-1.1.0.tgz",
+                    "integrity": "sha512-shared",
+                },
+                "node_modules/dev-tool": {
+                    "version": "2.0.0",
+                    "resolved": "https://registry.test/dev-tool/-/dev-tool-2.0.0.tgz",
+                    "dev": true,
+                },
+                "node_modules/str": {
+                    "name": "string-width",
+                    "version": "4.2.3",
+                    "resolved": "https://registry.test/string-width/-/string-width-4.2.3.tgz",
+                    "integrity": "sha512-string-width",
+                },
+                "node_modules/lib": { "resolved": "lib", "link": true },
+                "lib": { "name": "lib", "version": "0.1.0" },
+                "node_modules/web": { "resolved": "packages/web", "link": true },
+                "packages/web": { "name": "web", "version": "1.0.0" },
+            },
+        })
+    );
+}
+
+#[test]
+fn writes_the_package_lock_next_to_package_json() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path().join("package.json"), package_json().to_string());
+
+    write_package_lock(&lockfile(), root.path()).unwrap();
+
+    let contents = std::fs::read_to_string(root.path().join(PACKAGE_LOCK_NAME)).unwrap();
+    let written: Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(written["lockfileVersion"], 3);
+    assert_eq!(written["packages"][""]["name"], "project");
+    assert_eq!(
+        written["packages"]["node_modules/a"]["integrity"],
+        "sha512-a"
+    );
+    assert!(contents.ends_with('\n'));
+}
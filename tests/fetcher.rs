@@ -0,0 +1,202 @@
+mod common;
+
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use blaze::error::Error;
+use blaze::fetcher::Fetcher;
+use blaze::lockfile::LockedPackage;
+use blaze::registry::HttpRegistry;
+use blaze::resolver::{ResolvedGraph, Resolver};
+use blaze::sources::compute_integrity;
+use blaze::store::Store;
+
+use common::{memory_registry, package_tarball, write, Package};
+
+#[tokio::test]
+async fn reuses_sources_fetched_while_resolving() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+
+    write(
+        root.path().join("local.tgz"),
+        package_tarball(&json!({ "name": "local", "version": "1.0.0" }), &[]),
+    );
+    let registry = Arc::new(memory_registry(&[]));
+
+    let graph = Resolver::new(registry.clone(), root.path())
+        .with_cache_directory(cache.path())
+        .resolve(&BTreeMap::from([(
+            "local".to_string(),
+            "file:local.tgz".to_string(),
+        )]))
+        .await
+        .unwrap();
+    assert!(graph.sources.contains_key("local"));
+
+    // fetching the tarball again would fail now
+    std::fs::remove_file(root.path().join("local.tgz")).unwrap();
+
+    let fetched = Fetcher::new(registry, Store::in_cache(cache.path()), root.path())
+        .with_cache_directory(cache.path())
+        .fetch(&graph)
+        .await
+        .unwrap();
+
+    assert!(fetched["local"].join("package.json").is_file());
+}
+
+#[tokio::test]
+async fn downloads_requested_tarball_urls_instead_of_asking_the_registry() {
+    let root = tempfile::tempdir().unwrap();
+    let store = tempfile::tempdir().unwrap();
+
+    // the registry has the package, but the project asks for it by url, which cannot be reached
+    let registry = Arc::new(memory_registry(&[Package {
+        name: "remote",
+        version: "1.0.0",
+        dependencies: &[],
+    }]));
+    let url = "http://127.0.0.1:9/remote-1.0.0.tgz";
+
+    let graph = ResolvedGraph {
+        packages: BTreeMap::from([(
+            "remote".to_string(),
+            LockedPackage {
+                name: "remote".to_string(),
+                version: "1.0.0".to_string(),
+                resolved: url.to_string(),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let fetcher = Fetcher::new(registry, Store::new(store.path()), root.path());
+
+    // without being requested by url, the package is taken from the registry
+    fetcher.fetch(&graph).await.unwrap();
+
+    let result = fetcher
+        .with_requested_specs([url.to_string()])
+        .fetch(&graph)
+        .await;
+
+    match result {
+        Err(Error::Install { failures }) => {
+            assert!(matches!(failures[0].error, Error::Network { .. }))
+        }
+        result => panic!("expected the download to fail, got {:?}", result),
+    }
+}
+
+/// Serves `tarball` over http in two halves with a pause in between. Requests for
+/// `/broken.tgz` announce the whole tarball but are cut off after the first half
+async fn serve_tarball(tarball: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let tarball = tarball.clone();
+
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let broken = String::from_utf8_lossy(&request).contains("/broken.tgz");
+
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    tarball.len()
+                );
+                let (first, second) = tarball.split_at(tarball.len() / 2);
+
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(first).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                if !broken {
+                    stream.write_all(second).await.unwrap();
+                }
+            });
+        }
+    });
+
+    format!("http://{}", address)
+}
+
+#[tokio::test]
+async fn unpacks_tarballs_as_they_download_and_keeps_only_matching_ones() {
+    let root = tempfile::tempdir().unwrap();
+    let store = tempfile::tempdir().unwrap();
+
+    let tarball = package_tarball(
+        &json!({ "name": "pkg", "version": "1.0.0" }),
+        &[("index.js", &"module.exports = 42\n".repeat(1000))],
+    );
+    let url = serve_tarball(tarball.clone()).await;
+
+    let package = |name: &str, integrity: String| {
+        (
+            name.to_string(),
+            LockedPackage {
+                name: name.to_string(),
+                version: "1.0.0".to_string(),
+                resolved: format!("{}/{}.tgz", url, name),
+                integrity,
+                ..Default::default()
+            },
+        )
+    };
+    let graph = ResolvedGraph {
+        packages: BTreeMap::from([
+            package("good", compute_integrity(&tarball)),
+            package("tampered", compute_integrity(b"other")),
+            package("broken", compute_integrity(&tarball)),
+        ]),
+        ..Default::default()
+    };
+
+    let registry = Arc::new(HttpRegistry::new(&url));
+    let result = Fetcher::new(registry.clone(), Store::new(store.path()), root.path())
+        .fetch(&graph)
+        .await;
+
+    let failures = match result {
+        Err(Error::Install { failures }) => failures,
+        result => panic!("expected two downloads to fail, got {:?}", result),
+    };
+    let failure = |package: &str| {
+        &failures
+            .iter()
+            .find(|failure| failure.package == package)
+            .unwrap()
+            .error
+    };
+    assert_eq!(failures.len(), 2);
+    assert!(matches!(failure("tampered@1.0.0"), Error::Integrity { .. }));
+    assert!(matches!(failure("broken@1.0.0"), Error::Network { .. }));
+
+    // only the package that matched its integrity entered the store, and no staging is left
+    let entries: Vec<_> = std::fs::read_dir(store.path()).unwrap().flatten().collect();
+    assert_eq!(entries.len(), 1);
+
+    let good = BTreeMap::from([package("good", compute_integrity(&tarball))]);
+    let fetched = Fetcher::new(registry, Store::new(store.path()), root.path())
+        .fetch(&ResolvedGraph {
+            packages: good,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(fetched["good"].join("index.js").is_file());
+}
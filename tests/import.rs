@@ -0,0 +1,326 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use blaze::dependencies::Dependencies;
+use blaze::error::Error;
+use blaze::import::ImportedLockfile;
+use blaze::lockfile::LockedPackage;
+use blaze::reporter::SilentReporter;
+
+use common::{write, RecordingReporter};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/import")
+        .join(name)
+}
+
+fn read_fixture(name: &str) -> ImportedLockfile {
+    let root = fixture(name);
+    let dependencies = Dependencies::from_package_json(&root).unwrap();
+
+    ImportedLockfile::read(&root, &dependencies.root_dependencies())
+        .unwrap()
+        .unwrap()
+}
+
+fn versions(versions: &[(&str, &str)]) -> BTreeMap<String, String> {
+    versions
+        .iter()
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect()
+}
+
+/// The versions every fixture locks, keyed by the name each package is installed under. The
+/// project depends on `debug@^2.6.0` while `@scope/util` needs `debug@^4.1.0`, so the version of
+/// the project has to win over the higher one
+const LOCKED_VERSIONS: [(&str, &str); 7] = [
+    ("@scope/util", "1.2.0"),
+    ("debug", "2.6.9"),
+    ("lodash", "4.17.21"),
+    ("loose-envify", "1.4.0"),
+    ("react", "17.0.2"),
+    ("react-dom", "17.0.2"),
+    ("strip", "6.0.1"),
+];
+
+fn registry_package(name: &str, version: &str) -> LockedPackage {
+    LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        resolved: format!("https://registry.test/{}/-/{}.tgz", name, version),
+        integrity: "sha512-cmVzb2x2ZWQ=".to_string(),
+        dependencies: BTreeMap::new(),
+    }
+}
+
+/// Applies an imported lockfile to resolved packages, returning the `(resolved, integrity)` of
+/// each of them afterwards
+fn apply(imported: &ImportedLockfile) -> BTreeMap<String, (String, String)> {
+    let mut packages: BTreeMap<String, LockedPackage> = [
+        ("lodash", registry_package("lodash", "4.17.21")),
+        ("strip", registry_package("strip-ansi", "6.0.1")),
+        // a version the lockfile does not have keeps what the registry said
+        ("react", registry_package("react", "17.0.1")),
+        (
+            "local",
+            LockedPackage {
+                name: "local".to_string(),
+                version: "1.0.0".to_string(),
+                resolved: "file:local".to_string(),
+                integrity: String::new(),
+                dependencies: BTreeMap::new(),
+            },
+        ),
+    ]
+    .into_iter()
+    .map(|(name, package)| (name.to_string(), package))
+    .collect();
+
+    imported.apply(&mut packages);
+
+    packages
+        .into_iter()
+        .map(|(name, package)| (name, (package.resolved, package.integrity)))
+        .collect()
+}
+
+#[test]
+fn imports_package_lock_v2_and_v3() {
+    for name in ["npm-v2", "npm-v3"] {
+        let imported = read_fixture(name);
+
+        assert_eq!(imported.file_name, "package-lock.json");
+        // the `debug` nested below `@scope/util` keeps its version there, and the linked
+        // workspace is left out
+        let mut locked_versions = versions(&LOCKED_VERSIONS);
+        locked_versions.insert(
+            "@scope/util/node_modules/debug".to_string(),
+            "4.3.4".to_string(),
+        );
+        assert_eq!(
+            imported.preferred_versions(&SilentReporter),
+            locked_versions,
+            "{}",
+            name
+        );
+
+        let (_, strip) = imported
+            .packages
+            .iter()
+            .find(|(name, _)| name == "strip")
+            .unwrap();
+        assert_eq!(strip.name, "strip-ansi");
+
+        let applied = apply(&imported);
+        assert_eq!(
+            applied["lodash"],
+            (
+                "https://registry.npmjs.org/lodash/-/lodash-4.17.21.tgz".to_string(),
+                "sha512-bG9kYXNo".to_string()
+            )
+        );
+        assert_eq!(applied["strip"].1, "sha512-c3RyaXAtYW5zaQ==");
+        assert_eq!(applied["react"].1, "sha512-cmVzb2x2ZWQ=");
+        assert_eq!(applied["local"], ("file:local".to_string(), String::new()));
+    }
+}
+
+#[test]
+fn imports_yarn_v1() {
+    let imported = read_fixture("yarn-v1");
+
+    assert_eq!(imported.file_name, "yarn.lock");
+    assert_eq!(
+        imported.preferred_versions(&SilentReporter),
+        versions(&LOCKED_VERSIONS)
+    );
+    assert_eq!(
+        imported.root_versions,
+        versions(&[
+            ("@scope/util", "1.2.0"),
+            ("debug", "2.6.9"),
+            ("lodash", "4.17.21"),
+            ("react", "17.0.2"),
+            ("react-dom", "17.0.2"),
+            ("strip", "6.0.1"),
+        ])
+    );
+
+    // both versions of `debug` are locked
+    let debug_versions: Vec<&str> = imported
+        .packages
+        .iter()
+        .filter(|(name, _)| name == "debug")
+        .map(|(_, package)| package.version.as_str())
+        .collect();
+    assert_eq!(debug_versions, ["2.6.9", "4.3.4"]);
+
+    let (_, scope_util) = imported
+        .packages
+        .iter()
+        .find(|(name, _)| name == "@scope/util")
+        .unwrap();
+    assert_eq!(scope_util.dependencies, versions(&[("debug", "^4.1.0")]));
+
+    let applied = apply(&imported);
+    // the sha1 yarn appends to the url is dropped
+    assert_eq!(
+        applied["lodash"],
+        (
+            "https://registry.yarnpkg.com/lodash/-/lodash-4.17.21.tgz".to_string(),
+            "sha512-bG9kYXNo".to_string()
+        )
+    );
+    assert_eq!(applied["strip"].1, "sha512-c3RyaXAtYW5zaQ==");
+}
+
+#[test]
+fn imports_pnpm_v5_v6_and_v9() {
+    for name in ["pnpm-v5", "pnpm-v6", "pnpm-v9"] {
+        let imported = read_fixture(name);
+
+        assert_eq!(imported.file_name, "pnpm-lock.yaml");
+
+        // every key is split into its name and version, dropping the peer dependencies
+        let mut packages: Vec<(String, String)> = imported
+            .packages
+            .iter()
+            .map(|(name, package)| (name.clone(), package.version.clone()))
+            .collect();
+        packages.sort();
+        assert_eq!(
+            packages,
+            [
+                ("@scope/util", "1.2.0"),
+                ("debug", "2.6.9"),
+                ("debug", "4.3.4"),
+                ("lodash", "4.17.21"),
+                ("loose-envify", "1.4.0"),
+                ("react", "17.0.2"),
+                ("react-dom", "17.0.2"),
+                ("strip-ansi", "6.0.1"),
+            ]
+            .map(|(name, version)| (name.to_string(), version.to_string())),
+            "{}",
+            name
+        );
+
+        // the alias is locked under its own name only among the root versions
+        let mut expected = versions(&LOCKED_VERSIONS);
+        expected.insert("strip-ansi".to_string(), "6.0.1".to_string());
+        assert_eq!(
+            imported.preferred_versions(&SilentReporter),
+            expected,
+            "{}",
+            name
+        );
+
+        // pnpm only records the integrity of registry packages, not their tarball url
+        let applied = apply(&imported);
+        assert_eq!(
+            applied["lodash"],
+            (
+                "https://registry.test/lodash/-/4.17.21.tgz".to_string(),
+                "sha512-bG9kYXNo".to_string()
+            ),
+            "{}",
+            name
+        );
+        assert_eq!(applied["strip"].1, "sha512-c3RyaXAtYW5zaQ==", "{}", name);
+        assert_eq!(applied["react"].1, "sha512-cmVzb2x2ZWQ=", "{}", name);
+    }
+}
+
+#[test]
+fn prefers_the_versions_installed_at_the_top_of_node_modules() {
+    let imported = read_fixture("npm-nested");
+    let reporter = RecordingReporter::default();
+
+    // `ms@2.1.3` nested below `server` is higher, but `ms@2.0.0` is the one at the top, and
+    // the nested copy keeps its version where it is
+    assert_eq!(
+        imported.preferred_versions(&reporter),
+        versions(&[
+            ("cli", "1.0.0"),
+            ("ms", "2.0.0"),
+            ("server", "1.0.0"),
+            ("server/node_modules/ms", "2.1.3")
+        ])
+    );
+    assert!(reporter.warnings().is_empty());
+
+    // the nested copy still lends its integrity to a resolved `ms@2.1.3`
+    let mut packages: BTreeMap<String, LockedPackage> =
+        [("ms".to_string(), registry_package("ms", "2.1.3"))].into();
+    imported.apply(&mut packages);
+    assert_eq!(packages["ms"].integrity, "sha512-bXMtMi4x");
+}
+
+#[test]
+fn prefers_the_version_most_dependents_accept_without_node_modules_paths() {
+    let mut imported = read_fixture("npm-nested");
+    imported.installed.clear();
+    let reporter = RecordingReporter::default();
+
+    // `cli` accepts both versions of `ms` and `server` only the higher one
+    assert_eq!(imported.preferred_versions(&reporter)["ms"], "2.1.3");
+    assert_eq!(
+        reporter.warnings(),
+        [
+            "package-lock.json also locks ms 2.0.0, only 2.1.3 is kept and the packages outside \
+          of its range get the newest version within it"
+        ]
+    );
+}
+
+#[test]
+fn prefers_package_lock_over_other_lockfiles() {
+    let root = tempfile::tempdir().unwrap();
+    for file_name in ["package-lock.json", "yarn.lock", "pnpm-lock.yaml"] {
+        std::fs::copy(
+            fixture(match file_name {
+                "package-lock.json" => "npm-v3",
+                "yarn.lock" => "yarn-v1",
+                _ => "pnpm-v9",
+            })
+            .join(file_name),
+            root.path().join(file_name),
+        )
+        .unwrap();
+    }
+
+    let imported = ImportedLockfile::read(root.path(), &BTreeMap::new())
+        .unwrap()
+        .unwrap();
+    assert_eq!(imported.file_name, "package-lock.json");
+}
+
+#[test]
+fn rejects_unsupported_lockfiles() {
+    let root = tempfile::tempdir().unwrap();
+    assert!(ImportedLockfile::read(root.path(), &BTreeMap::new())
+        .unwrap()
+        .is_none());
+
+    write(
+        root.path().join("package-lock.json"),
+        r#"{ "lockfileVersion": 1, "dependencies": {} }"#,
+    );
+    assert!(matches!(
+        ImportedLockfile::read(root.path(), &BTreeMap::new()),
+        Err(Error::Lockfile { .. })
+    ));
+
+    let root = tempfile::tempdir().unwrap();
+    write(
+        root.path().join("yarn.lock"),
+        "__metadata:\n  version: 6\n\n\"lodash@npm:^4.17.0\":\n  version: 4.17.21\n",
+    );
+    assert!(matches!(
+        ImportedLockfile::read(root.path(), &BTreeMap::new()),
+        Err(Error::Lockfile { .. })
+    ));
+}
@@ -0,0 +1,442 @@
+mod common;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use blaze::concurrency::Concurrency;
+use blaze::dependencies::Dependencies;
+use blaze::lockfile::Lockfile;
+use blaze::outdated::find_outdated_dependencies;
+use blaze::registry::{MemoryRegistry, Registry, Tarball};
+use blaze::reporter::SilentReporter;
+use blaze::workspaces::{filter_workspaces, find_workspaces};
+
+use common::{memory_registry, write, Package, RecordingReporter};
+
+fn packages() -> Vec<Package<'static>> {
+    vec![
+        Package {
+            name: "a",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0")],
+        },
+        Package {
+            name: "b",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "shared",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "shared",
+            version: "1.1.0",
+            dependencies: &[],
+        },
+    ]
+}
+
+/// Writes `package_json` into `root` and installs it from `registry`, with the blaze cache in
+/// `cache`
+async fn install(root: &Path, cache: &Path, registry: Arc<dyn Registry>, package_json: Value) {
+    write(root.join("package.json"), package_json.to_string());
+
+    Dependencies::from_package_json(root)
+        .unwrap()
+        .with_cache_directory(cache)
+        .with_registry(registry)
+        .download_dependencies(Arc::new(SilentReporter))
+        .await
+        .unwrap();
+}
+
+fn installed_version(root: &Path, name: &str) -> Option<String> {
+    let path = root.join("node_modules").join(name).join("package.json");
+    let package_json: Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+
+    package_json["version"].as_str().map(String::from)
+}
+
+#[tokio::test]
+async fn installs_from_a_memory_registry() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let registry = Arc::new(memory_registry(&packages()));
+
+    install(
+        root.path(),
+        cache.path(),
+        registry,
+        json!({ "dependencies": { "a": "^1.0.0", "b": "^1.0.0" } }),
+    )
+    .await;
+
+    assert_eq!(installed_version(root.path(), "a").unwrap(), "1.0.0");
+    assert_eq!(installed_version(root.path(), "b").unwrap(), "1.0.0");
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.1.0");
+    assert!(root.path().join("node_modules/a/index.js").is_file());
+
+    let lockfile = Lockfile::read(root.path()).unwrap();
+    assert_eq!(
+        lockfile.packages.keys().collect::<Vec<_>>(),
+        vec!["a", "b", "shared"]
+    );
+    assert_eq!(lockfile.packages["shared"].version, "1.1.0");
+}
+
+#[tokio::test]
+async fn installs_from_a_local_registry() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let registry_directory = tempfile::tempdir().unwrap();
+    let registry = Arc::new(common::local_registry(
+        registry_directory.path(),
+        &packages(),
+    ));
+
+    install(
+        root.path(),
+        cache.path(),
+        registry,
+        json!({ "dependencies": { "a": "^1.0.0" } }),
+    )
+    .await;
+
+    assert_eq!(installed_version(root.path(), "a").unwrap(), "1.0.0");
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.1.0");
+}
+
+#[tokio::test]
+async fn keeps_locked_versions_and_removes_dropped_packages() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+
+    install(
+        root.path(),
+        cache.path(),
+        Arc::new(memory_registry(&packages()[..3])),
+        json!({ "dependencies": { "a": "^1.0.0", "b": "^1.0.0" } }),
+    )
+    .await;
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.0.0");
+
+    // a newer `shared` was published, but `blaze.lock` still matches `package.json`
+    let registry = Arc::new(memory_registry(&packages()));
+    install(
+        root.path(),
+        cache.path(),
+        registry.clone(),
+        json!({ "dependencies": { "a": "^1.0.0", "b": "^1.0.0" } }),
+    )
+    .await;
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.0.0");
+
+    install(
+        root.path(),
+        cache.path(),
+        registry,
+        json!({ "dependencies": { "a": "^1.0.0" } }),
+    )
+    .await;
+    assert_eq!(installed_version(root.path(), "a").unwrap(), "1.0.0");
+    assert!(!root.path().join("node_modules/b").exists());
+    assert!(!Lockfile::read(root.path())
+        .unwrap()
+        .packages
+        .contains_key("b"));
+}
+
+#[tokio::test]
+async fn fails_without_writing_the_lockfile() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    write(
+        root.path().join("package.json"),
+        json!({ "dependencies": { "a": "^1.0.0", "missing": "^1.0.0" } }).to_string(),
+    );
+
+    let result = Dependencies::from_package_json(root.path())
+        .unwrap()
+        .with_cache_directory(cache.path())
+        .with_registry(Arc::new(memory_registry(&packages())))
+        .download_dependencies(Arc::new(SilentReporter))
+        .await;
+
+    assert!(result.is_err());
+    assert!(Lockfile::read(root.path()).is_none());
+}
+
+#[tokio::test]
+async fn dedupe_removes_copies_of_packages_that_can_share_a_version() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let registry = Arc::new(memory_registry(&[
+        Package {
+            name: "a",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0")],
+        },
+        Package {
+            name: "b",
+            version: "1.0.0",
+            dependencies: &[("shared", "~1.0.0")],
+        },
+        Package {
+            name: "shared",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "shared",
+            version: "1.1.0",
+            dependencies: &[],
+        },
+    ]));
+
+    // the request of `a` comes first and gets the newest version, outside of the range of `b`,
+    // which gets a copy of its own
+    install(
+        root.path(),
+        cache.path(),
+        registry.clone(),
+        json!({ "dependencies": { "a": "^1.0.0", "b": "^1.0.0" } }),
+    )
+    .await;
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.1.0");
+    assert_eq!(
+        installed_version(root.path(), "b/node_modules/shared").unwrap(),
+        "1.0.0"
+    );
+
+    let dependencies = Dependencies::from_package_json(root.path())
+        .unwrap()
+        .with_cache_directory(cache.path())
+        .with_registry(registry);
+
+    let report = dependencies.dedupe(Arc::new(SilentReporter)).await.unwrap();
+    assert_eq!(report.duplicates_removed, 1);
+    assert!(report.bytes_saved > 0);
+    assert_eq!(
+        report.changed,
+        vec![(
+            "shared".to_string(),
+            "1.1.0".to_string(),
+            "1.0.0".to_string()
+        )]
+    );
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.0.0");
+    assert!(!root.path().join("node_modules/b/node_modules").exists());
+    assert_eq!(
+        Lockfile::read(root.path()).unwrap().packages.len(),
+        3,
+        "a, b and a single copy of shared are locked"
+    );
+
+    let report = dependencies.dedupe(Arc::new(SilentReporter)).await.unwrap();
+    assert_eq!(report, Default::default());
+}
+
+#[tokio::test]
+async fn skips_binaries_linking_outside_of_the_package() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    write(root.path().join("secret.js"), "secret");
+    write(root.path().join("lib/cli.js"), "#!/usr/bin/env node\n");
+    write(
+        root.path().join("lib/package.json"),
+        json!({
+            "name": "lib",
+            "version": "1.0.0",
+            "bin": {
+                "lib": "cli.js",
+                "../../escape": "cli.js",
+                "secret": "../../../secret.js",
+            },
+        })
+        .to_string(),
+    );
+    write(
+        root.path().join("package.json"),
+        json!({ "dependencies": { "lib": "file:lib" } }).to_string(),
+    );
+
+    let reporter = Arc::new(RecordingReporter::default());
+    Dependencies::from_package_json(root.path())
+        .unwrap()
+        .with_cache_directory(cache.path())
+        .with_registry(Arc::new(memory_registry(&[])))
+        .download_dependencies(reporter.clone())
+        .await
+        .unwrap();
+
+    let bin_directory = root.path().join("node_modules/.bin");
+    assert!(bin_directory.join("lib").is_file());
+    assert!(!bin_directory.join("secret").exists());
+    assert!(!root.path().join("escape").exists());
+    assert_eq!(reporter.warnings().len(), 2);
+}
+
+#[tokio::test]
+async fn filtered_installs_keep_the_packages_of_other_workspaces() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    let registry = Arc::new(memory_registry(&packages()));
+
+    write(
+        root.path().join("package.json"),
+        json!({ "workspaces": ["packages/*"] }).to_string(),
+    );
+    write(
+        root.path().join("packages/web/package.json"),
+        json!({ "name": "web", "version": "1.0.0", "dependencies": { "b": "^1.0.0" } }).to_string(),
+    );
+    write(
+        root.path().join("packages/api/package.json"),
+        json!({ "name": "api", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }).to_string(),
+    );
+
+    let install = |filter: &[&str]| {
+        let workspaces = find_workspaces(root.path()).unwrap();
+        let mut dependencies = Dependencies::from_package_json(root.path())
+            .unwrap()
+            .with_cache_directory(cache.path())
+            .with_registry(registry.clone());
+        dependencies
+            .add_workspaces(&workspaces, &workspaces)
+            .unwrap();
+
+        if !filter.is_empty() {
+            let filter: Vec<String> = filter.iter().map(|name| name.to_string()).collect();
+            let selected = filter_workspaces(&workspaces, &filter).unwrap();
+            dependencies
+                .select_workspaces(&selected, &workspaces)
+                .unwrap();
+        }
+
+        async move {
+            dependencies
+                .download_dependencies(Arc::new(SilentReporter))
+                .await
+                .unwrap()
+        }
+    };
+
+    // only the dependencies of `web` are installed, but every workspace is locked
+    install(&["web"]).await;
+    assert_eq!(installed_version(root.path(), "b").unwrap(), "1.0.0");
+    assert!(installed_version(root.path(), "a").is_none());
+    assert!(installed_version(root.path(), "shared").is_none());
+    assert!(root.path().join("node_modules/api").exists());
+    assert_eq!(
+        Lockfile::read(root.path())
+            .unwrap()
+            .packages
+            .keys()
+            .collect::<Vec<_>>(),
+        vec!["a", "b", "shared"]
+    );
+
+    install(&[]).await;
+    assert_eq!(installed_version(root.path(), "a").unwrap(), "1.0.0");
+
+    // what `api` needs stays installed through a filtered install of `web`
+    install(&["web"]).await;
+    assert_eq!(installed_version(root.path(), "a").unwrap(), "1.0.0");
+    assert_eq!(installed_version(root.path(), "shared").unwrap(), "1.1.0");
+    assert_eq!(installed_version(root.path(), "b").unwrap(), "1.0.0");
+}
+
+/// A registry recording the most requests it served at once
+#[derive(Default)]
+struct ConcurrencyRegistry {
+    registry: MemoryRegistry,
+    in_flight: AtomicUsize,
+    most_in_flight: AtomicUsize,
+}
+
+impl ConcurrencyRegistry {
+    async fn request<T>(&self, request: impl std::future::Future<Output = T>) -> T {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+        // keep the request open long enough for the others to pile up
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let result = request.await;
+
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+}
+
+#[async_trait]
+impl Registry for ConcurrencyRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> blaze::Result<Value> {
+        self.request(self.registry.fetch_packument(package_name))
+            .await
+    }
+
+    async fn fetch_tarball(
+        &self,
+        package_name: &str,
+        version: &str,
+        url: &str,
+    ) -> blaze::Result<Tarball> {
+        self.request(self.registry.fetch_tarball(package_name, version, url))
+            .await
+    }
+}
+
+#[tokio::test]
+async fn never_exceeds_the_network_limit() {
+    let root = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+
+    let names: Vec<String> = (0..12).map(|index| format!("limited-{}", index)).collect();
+    let registry = Arc::new(ConcurrencyRegistry {
+        registry: memory_registry(
+            &names
+                .iter()
+                .map(|name| Package {
+                    name,
+                    version: "1.0.0",
+                    dependencies: &[],
+                })
+                .collect::<Vec<_>>(),
+        ),
+        ..ConcurrencyRegistry::default()
+    });
+
+    let dependencies: serde_json::Map<String, Value> = names
+        .iter()
+        .map(|name| (name.clone(), json!("^1.0.0")))
+        .collect();
+    write(
+        root.path().join("package.json"),
+        json!({ "dependencies": dependencies }).to_string(),
+    );
+
+    let dependencies = Dependencies::from_package_json(root.path())
+        .unwrap()
+        .with_cache_directory(cache.path())
+        .with_registry(registry.clone())
+        .with_concurrency(Concurrency::with_network_limit(3));
+
+    dependencies
+        .download_dependencies(Arc::new(SilentReporter))
+        .await
+        .unwrap();
+    find_outdated_dependencies(&dependencies).await.unwrap();
+    dependencies.dedupe(Arc::new(SilentReporter)).await.unwrap();
+
+    assert_eq!(
+        installed_version(root.path(), "limited-11").unwrap(),
+        "1.0.0"
+    );
+    assert_eq!(registry.most_in_flight.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use serde_json::json;
+use std::path::Path;
+use std::process::Command;
+
+use blaze::lockfile::LOCKFILE_NAME;
+use blaze::project::Project;
+
+use common::write;
+
+/// A project with a `src` directory and the workspace `packages/web`, which has one of its own
+fn monorepo() -> tempfile::TempDir {
+    let root = tempfile::tempdir().unwrap();
+    write(
+        root.path().join("package.json"),
+        json!({ "name": "monorepo", "workspaces": ["packages/*"] }).to_string(),
+    );
+    write(
+        root.path().join("packages/web/package.json"),
+        json!({ "name": "web", "version": "1.0.0" }).to_string(),
+    );
+    std::fs::create_dir_all(root.path().join("src/deeply/nested")).unwrap();
+    std::fs::create_dir_all(root.path().join("packages/web/src")).unwrap();
+
+    root
+}
+
+fn canonical(path: impl AsRef<Path>) -> std::path::PathBuf {
+    std::fs::canonicalize(path).unwrap()
+}
+
+#[test]
+fn discovers_the_project_from_a_nested_directory() {
+    let root = monorepo();
+
+    let project = Project::discover(&root.path().join("src/deeply/nested")).unwrap();
+
+    assert_eq!(project.root, canonical(root.path()));
+    assert_eq!(project.package_directory, canonical(root.path()));
+    assert!(!project.is_workspace());
+}
+
+#[test]
+fn discovers_the_workspace_root_from_a_workspace() {
+    let root = monorepo();
+
+    let project = Project::discover(&root.path().join("packages/web/src")).unwrap();
+
+    assert_eq!(project.root, canonical(root.path()));
+    assert_eq!(
+        project.package_directory,
+        canonical(root.path().join("packages/web"))
+    );
+    assert!(project.is_workspace());
+}
+
+#[test]
+fn does_not_take_packages_that_are_not_workspaces_for_one() {
+    let root = monorepo();
+    write(
+        root.path().join("tools/script/package.json"),
+        json!({ "name": "script" }).to_string(),
+    );
+
+    let project = Project::discover(&root.path().join("tools/script")).unwrap();
+
+    assert_eq!(project.root, canonical(root.path().join("tools/script")));
+    assert!(!project.is_workspace());
+}
+
+#[test]
+fn starts_in_the_directory_without_a_package_json() {
+    let directory = tempfile::tempdir().unwrap();
+
+    let project = Project::discover(directory.path()).unwrap();
+    assert_eq!(project.root, canonical(directory.path()));
+
+    assert!(Project::discover(&directory.path().join("missing")).is_err());
+}
+
+#[test]
+fn runs_in_the_project_of_the_prefix() {
+    let root = monorepo();
+    let elsewhere = tempfile::tempdir().unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_blaze"))
+        .current_dir(elsewhere.path())
+        .arg("--prefix")
+        .arg(root.path().join("packages/web/src"))
+        .arg("install")
+        .output()
+        .unwrap()
+        .status;
+
+    assert!(status.success());
+    assert!(root.path().join(LOCKFILE_NAME).is_file());
+    assert!(root.path().join("node_modules/web").exists());
+    assert!(!root
+        .path()
+        .join("packages/web")
+        .join(LOCKFILE_NAME)
+        .exists());
+    assert!(!elsewhere.path().join(LOCKFILE_NAME).exists());
+
+    let status = Command::new(env!("CARGO_BIN_EXE_blaze"))
+        .current_dir(elsewhere.path())
+        .args(["-C", "missing", "install"])
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1));
+}
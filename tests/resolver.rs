@@ -0,0 +1,430 @@
+mod common;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use blaze::error::Error;
+use blaze::overrides::Overrides;
+use blaze::registry::{MemoryRegistry, Registry, Tarball};
+use blaze::resolver::{ResolvedGraph, Resolver};
+use blaze::utils::validate_package_name;
+
+use common::{memory_registry, Package, RecordingReporter};
+
+fn dependencies(dependencies: &[(&str, &str)]) -> BTreeMap<String, String> {
+    dependencies
+        .iter()
+        .map(|(name, spec)| (name.to_string(), spec.to_string()))
+        .collect()
+}
+
+fn versions(graph: &ResolvedGraph) -> BTreeMap<String, String> {
+    graph
+        .packages
+        .iter()
+        .map(|(name, package)| (name.clone(), package.version.clone()))
+        .collect()
+}
+
+/// Two packages depending on the same range of `shared`, which has two major versions
+fn shared_dependency_packages() -> Vec<Package<'static>> {
+    vec![
+        Package {
+            name: "a",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0")],
+        },
+        Package {
+            name: "b",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0")],
+        },
+        Package {
+            name: "shared",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "shared",
+            version: "2.0.0",
+            dependencies: &[],
+        },
+    ]
+}
+
+fn overrides(overrides: Value) -> Overrides {
+    let overrides: BTreeMap<String, Value> = serde_json::from_value(overrides).unwrap();
+    Overrides::new(&overrides, &BTreeMap::new(), &BTreeMap::new()).unwrap()
+}
+
+#[tokio::test]
+async fn nested_overrides_apply_to_requests_that_come_later() {
+    let registry = Arc::new(memory_registry(&shared_dependency_packages()));
+    let reporter = Arc::new(RecordingReporter::default());
+
+    // `a` asks for `shared` first, but only the request of `b` is overridden
+    let graph = Resolver::new(registry, Path::new("."))
+        .with_overrides(overrides(json!({ "b": { "shared": "2.0.0" } })))
+        .with_reporter(reporter.clone())
+        .resolve(&dependencies(&[("a", "^1.0.0"), ("b", "^1.0.0")]))
+        .await
+        .unwrap();
+
+    assert_eq!(versions(&graph)["shared"], "2.0.0");
+    assert!(reporter.warnings().is_empty());
+}
+
+#[tokio::test]
+async fn nests_overridden_versions_that_conflict_with_the_installed_one() {
+    let registry = Arc::new(memory_registry(&shared_dependency_packages()));
+    let reporter = Arc::new(RecordingReporter::default());
+
+    let graph = Resolver::new(registry, Path::new("."))
+        .with_overrides(overrides(json!({
+            "a": { "shared": "1.0.0" },
+            "b": { "shared": "2.0.0" },
+        })))
+        .with_reporter(reporter.clone())
+        .resolve(&dependencies(&[("a", "^1.0.0"), ("b", "^1.0.0")]))
+        .await
+        .unwrap();
+
+    assert_eq!(versions(&graph)["shared"], "1.0.0");
+    assert_eq!(versions(&graph)["b/node_modules/shared"], "2.0.0");
+    assert!(reporter.warnings().is_empty());
+}
+
+#[tokio::test]
+async fn resolves_from_a_local_registry() {
+    let directory = tempfile::tempdir().unwrap();
+    let registry = Arc::new(common::local_registry(
+        directory.path(),
+        &shared_dependency_packages(),
+    ));
+
+    let graph = Resolver::new(registry, Path::new("."))
+        .resolve(&dependencies(&[("a", "^1.0.0"), ("shared", "*")]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        versions(&graph),
+        BTreeMap::from([
+            ("a".to_string(), "1.0.0".to_string()),
+            // the project asks for any version, `a` for one below the newest
+            ("a/node_modules/shared".to_string(), "1.0.0".to_string()),
+            ("shared".to_string(), "2.0.0".to_string()),
+        ])
+    );
+
+    let shared = &graph.packages["shared"];
+    assert_eq!(shared.resolved, "https://registry.test/shared/-/2.0.0.tgz");
+    assert!(shared.integrity.starts_with("sha512-"));
+    assert_eq!(
+        graph.packages["a"].dependencies,
+        dependencies(&[("shared", "^1.0.0")])
+    );
+}
+
+#[tokio::test]
+async fn fails_for_packages_missing_from_the_registry() {
+    let registry = Arc::new(memory_registry(&shared_dependency_packages()));
+
+    let result = Resolver::new(registry, Path::new("."))
+        .resolve(&dependencies(&[("a", "^1.0.0"), ("missing", "^1.0.0")]))
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn rejects_names_that_lead_out_of_node_modules() {
+    for name in ["lodash", "@types/node", "is_even", "Uppercase"] {
+        assert!(validate_package_name(name).is_ok(), "`{}` is valid", name);
+    }
+    for name in [
+        "",
+        "..",
+        ".bin",
+        "../a",
+        "a/b",
+        "@scope",
+        "@scope/..",
+        "@/a",
+        "@a/b/c",
+        "/etc",
+        "a\\b",
+        "c:a",
+    ] {
+        assert!(
+            validate_package_name(name).is_err(),
+            "`{}` is invalid",
+            name
+        );
+    }
+
+    let registry = Arc::new(memory_registry(&[Package {
+        name: "a",
+        version: "1.0.0",
+        dependencies: &[("../../escape", "^1.0.0")],
+    }]));
+
+    let result = Resolver::new(registry, Path::new("."))
+        .resolve(&dependencies(&[("a", "^1.0.0")]))
+        .await;
+
+    match result {
+        Err(Error::Install { failures }) => {
+            assert_eq!(failures.len(), 1);
+            assert!(
+                matches!(&failures[0].error, Error::InvalidName { name } if name == "../../escape")
+            );
+        }
+        other => panic!(
+            "expected an invalid name, got {:?}",
+            other.map(|graph| versions(&graph))
+        ),
+    }
+}
+
+#[tokio::test]
+async fn resolves_dependency_cycles() {
+    let registry = Arc::new(memory_registry(&[
+        Package {
+            name: "a",
+            version: "1.0.0",
+            dependencies: &[("b", "^1.0.0")],
+        },
+        Package {
+            name: "b",
+            version: "1.0.0",
+            dependencies: &[("a", "^1.0.0")],
+        },
+    ]));
+
+    let graph = tokio::time::timeout(
+        Duration::from_secs(5),
+        Resolver::new(registry, Path::new(".")).resolve(&dependencies(&[("a", "^1.0.0")])),
+    )
+    .await
+    .expect("the resolver loops on the cycle")
+    .unwrap();
+
+    assert_eq!(
+        versions(&graph),
+        BTreeMap::from([
+            ("a".to_string(), "1.0.0".to_string()),
+            ("b".to_string(), "1.0.0".to_string()),
+        ])
+    );
+    assert_eq!(
+        graph.packages["a"].dependencies,
+        dependencies(&[("b", "^1.0.0")])
+    );
+    assert_eq!(
+        graph.packages["b"].dependencies,
+        dependencies(&[("a", "^1.0.0")])
+    );
+}
+
+/// A registry counting the packuments it serves
+struct CountingRegistry {
+    registry: MemoryRegistry,
+    packuments: Mutex<BTreeMap<String, usize>>,
+}
+
+#[async_trait]
+impl Registry for CountingRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> blaze::Result<Value> {
+        *self
+            .packuments
+            .lock()
+            .unwrap()
+            .entry(package_name.to_string())
+            .or_default() += 1;
+
+        // give the other requests for the same package time to come in while this one is
+        // in flight
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        self.registry.fetch_packument(package_name).await
+    }
+
+    async fn fetch_tarball(
+        &self,
+        package_name: &str,
+        version: &str,
+        url: &str,
+    ) -> blaze::Result<Tarball> {
+        self.registry
+            .fetch_tarball(package_name, version, url)
+            .await
+    }
+}
+
+#[tokio::test]
+async fn fetches_every_packument_once() {
+    let registry = Arc::new(CountingRegistry {
+        registry: memory_registry(&[
+            Package {
+                name: "a",
+                version: "1.0.0",
+                dependencies: &[("shared", "^1.0.0"), ("c", "^1.0.0")],
+            },
+            Package {
+                name: "b",
+                version: "1.0.0",
+                dependencies: &[("shared", "^1.0.0"), ("c", "^1.0.0")],
+            },
+            Package {
+                name: "c",
+                version: "1.0.0",
+                dependencies: &[("shared", "^1.0.0")],
+            },
+            Package {
+                name: "shared",
+                version: "1.0.0",
+                dependencies: &[],
+            },
+        ]),
+        packuments: Mutex::default(),
+    });
+
+    // `shared` is asked for under an alias and its own name, on several levels
+    Resolver::new(registry.clone(), Path::new("."))
+        .resolve(&dependencies(&[
+            ("a", "^1.0.0"),
+            ("alias", "npm:shared@^1.0.0"),
+            ("b", "^1.0.0"),
+        ]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *registry.packuments.lock().unwrap(),
+        BTreeMap::from([
+            ("a".to_string(), 1),
+            ("b".to_string(), 1),
+            ("c".to_string(), 1),
+            ("shared".to_string(), 1),
+        ])
+    );
+}
+
+/// A registry answering after a random delay, so that requests complete in a different order
+/// every time
+struct DelayedRegistry {
+    registry: MemoryRegistry,
+    seed: AtomicU64,
+}
+
+impl DelayedRegistry {
+    async fn delay(&self) {
+        // xorshift, seeded differently for every test run
+        let mut value = self
+            .seed
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed);
+        value ^= value << 13;
+        value ^= value >> 7;
+        value ^= value << 17;
+
+        tokio::time::sleep(Duration::from_millis(value % 10)).await;
+    }
+}
+
+#[async_trait]
+impl Registry for DelayedRegistry {
+    async fn fetch_packument(&self, package_name: &str) -> blaze::Result<Value> {
+        self.delay().await;
+        self.registry.fetch_packument(package_name).await
+    }
+
+    async fn fetch_tarball(
+        &self,
+        package_name: &str,
+        version: &str,
+        url: &str,
+    ) -> blaze::Result<Tarball> {
+        self.delay().await;
+        self.registry
+            .fetch_tarball(package_name, version, url)
+            .await
+    }
+}
+
+#[tokio::test]
+async fn resolves_the_same_graph_regardless_of_timing() {
+    // several packages ask for conflicting ranges of `shared` and `util` at different depths
+    let packages = [
+        Package {
+            name: "a",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0"), ("util", "^2.0.0")],
+        },
+        Package {
+            name: "b",
+            version: "1.0.0",
+            dependencies: &[("shared", "^2.0.0"), ("c", "^1.0.0")],
+        },
+        Package {
+            name: "c",
+            version: "1.0.0",
+            dependencies: &[("util", "^1.0.0"), ("d", "^1.0.0")],
+        },
+        Package {
+            name: "d",
+            version: "1.0.0",
+            dependencies: &[("shared", "^1.0.0"), ("util", "^2.0.0")],
+        },
+        Package {
+            name: "e",
+            version: "1.0.0",
+            dependencies: &[("d", "^1.0.0"), ("util", "^1.0.0")],
+        },
+        Package {
+            name: "shared",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "shared",
+            version: "2.0.0",
+            dependencies: &[("util", "^1.0.0")],
+        },
+        Package {
+            name: "util",
+            version: "1.0.0",
+            dependencies: &[],
+        },
+        Package {
+            name: "util",
+            version: "2.0.0",
+            dependencies: &[],
+        },
+    ];
+    let seed = RandomState::new().build_hasher().finish() | 1;
+    let registry = Arc::new(DelayedRegistry {
+        registry: memory_registry(&packages),
+        seed: AtomicU64::new(seed),
+    });
+    let root = dependencies(&[("a", "^1.0.0"), ("b", "^1.0.0"), ("e", "^1.0.0")]);
+
+    let mut graphs = Vec::new();
+    for _ in 0..20 {
+        let graph = Resolver::new(registry.clone(), Path::new("."))
+            .resolve(&root)
+            .await
+            .unwrap();
+        graphs.push(graph);
+    }
+
+    for graph in &graphs[1..] {
+        assert_eq!(graph, &graphs[0]);
+    }
+}
@@ -0,0 +1,345 @@
+mod common;
+
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use blaze::error::Error;
+use blaze::sources::{compute_integrity, fetch_source, unpack_tarball};
+use blaze::specifier::Specifier;
+
+use common::{file_url, git, has_git, package_tarball, write};
+
+#[test]
+fn parses_specifiers() {
+    let cases = [
+        ("^1.2.0", Specifier::Registry("^1.2.0".to_string())),
+        ("latest", Specifier::Registry("latest".to_string())),
+        ("", Specifier::Registry(String::new())),
+        (
+            "npm:string-width@^4.2.0",
+            Specifier::Alias {
+                name: "string-width".to_string(),
+                range: "^4.2.0".to_string(),
+            },
+        ),
+        (
+            "npm:@scope/pkg@1.0.0",
+            Specifier::Alias {
+                name: "@scope/pkg".to_string(),
+                range: "1.0.0".to_string(),
+            },
+        ),
+        (
+            "npm:lodash",
+            Specifier::Alias {
+                name: "lodash".to_string(),
+                range: "latest".to_string(),
+            },
+        ),
+        ("file:../lib", Specifier::Directory(PathBuf::from("../lib"))),
+        ("./lib", Specifier::Directory(PathBuf::from("./lib"))),
+        ("/opt/lib", Specifier::Directory(PathBuf::from("/opt/lib"))),
+        ("link:../lib", Specifier::Link(PathBuf::from("../lib"))),
+        (
+            "file:vendor/pkg-1.0.0.tgz",
+            Specifier::LocalTarball(PathBuf::from("vendor/pkg-1.0.0.tgz")),
+        ),
+        (
+            "../pkg.tar.gz",
+            Specifier::LocalTarball(PathBuf::from("../pkg.tar.gz")),
+        ),
+        (
+            "https://example.com/pkg.tgz",
+            Specifier::RemoteTarball("https://example.com/pkg.tgz".to_string()),
+        ),
+        (
+            "github:user/repo#v1.0.0",
+            Specifier::Git {
+                url: "https://github.com/user/repo.git".to_string(),
+                committish: Some("v1.0.0".to_string()),
+            },
+        ),
+        (
+            "gitlab:group/repo",
+            Specifier::Git {
+                url: "https://gitlab.com/group/repo.git".to_string(),
+                committish: None,
+            },
+        ),
+        (
+            "user/repo#main",
+            Specifier::Git {
+                url: "https://github.com/user/repo.git".to_string(),
+                committish: Some("main".to_string()),
+            },
+        ),
+        (
+            "git+ssh://git@example.com/repo.git#",
+            Specifier::Git {
+                url: "ssh://git@example.com/repo.git".to_string(),
+                committish: None,
+            },
+        ),
+        (
+            "git://example.com/repo.git",
+            Specifier::Git {
+                url: "git://example.com/repo.git".to_string(),
+                committish: None,
+            },
+        ),
+        (
+            "https://example.com/repo.git#abc123",
+            Specifier::Git {
+                url: "https://example.com/repo.git".to_string(),
+                committish: Some("abc123".to_string()),
+            },
+        ),
+        // urls that git would take as options are not git repositories
+        (
+            "git+--upload-pack=touch pwned",
+            Specifier::Registry("git+--upload-pack=touch pwned".to_string()),
+        ),
+        // scoped package names are not GitHub shorthands
+        ("@scope/pkg", Specifier::Registry("@scope/pkg".to_string())),
+    ];
+
+    for (spec, expected) in cases {
+        assert_eq!(Specifier::parse(spec), expected, "parsing `{}`", spec);
+    }
+}
+
+#[test]
+fn registry_package_follows_aliases() {
+    assert_eq!(
+        Specifier::parse("^1.0.0").registry_package("lodash"),
+        Some(("lodash".to_string(), "^1.0.0".to_string()))
+    );
+    assert_eq!(
+        Specifier::parse("npm:lodash@^4").registry_package("underscore"),
+        Some(("lodash".to_string(), "^4".to_string()))
+    );
+    assert_eq!(
+        Specifier::parse("file:../lib").registry_package("lib"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn fetches_local_directories() {
+    let root = tempfile::tempdir().unwrap();
+    let sources = tempfile::tempdir().unwrap();
+    write(
+        root.path().join("lib/package.json"),
+        json!({ "name": "lib", "version": "1.2.3" }).to_string(),
+    );
+
+    let fetched = fetch_source(
+        &Specifier::parse("file:lib"),
+        "",
+        root.path(),
+        sources.path(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(fetched.directory, root.path().join("lib"));
+    assert_eq!(fetched.resolved, "file:lib");
+    assert_eq!(fetched.integrity, "");
+    assert_eq!(fetched.manifest["version"], "1.2.3");
+
+    let linked = fetch_source(
+        &Specifier::parse("link:lib"),
+        "",
+        root.path(),
+        sources.path(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(linked.resolved, "link:lib");
+
+    let missing = fetch_source(
+        &Specifier::parse("file:missing"),
+        "",
+        root.path(),
+        sources.path(),
+    )
+    .await;
+    assert!(matches!(missing, Err(Error::Io { .. })));
+}
+
+#[tokio::test]
+async fn fetches_local_tarballs() {
+    let root = tempfile::tempdir().unwrap();
+    let sources = tempfile::tempdir().unwrap();
+    let tarball = package_tarball(
+        &json!({ "name": "pkg", "version": "1.0.0" }),
+        &[("lib/index.js", "module.exports = 42\n")],
+    );
+    write(root.path().join("vendor/pkg-1.0.0.tgz"), &tarball);
+
+    let specifier = Specifier::parse("file:vendor/pkg-1.0.0.tgz");
+
+    let fetched = fetch_source(&specifier, "", root.path(), sources.path())
+        .await
+        .unwrap();
+    assert_eq!(fetched.resolved, "file:vendor/pkg-1.0.0.tgz");
+    assert_eq!(fetched.integrity, compute_integrity(&tarball));
+    assert_eq!(fetched.manifest["name"], "pkg");
+    assert!(fetched.directory.join("lib/index.js").is_file());
+
+    let verified = fetch_source(
+        &specifier,
+        &compute_integrity(&tarball),
+        root.path(),
+        sources.path(),
+    )
+    .await;
+    assert!(verified.is_ok());
+
+    let tampered = fetch_source(
+        &specifier,
+        &compute_integrity(b"other"),
+        root.path(),
+        sources.path(),
+    )
+    .await;
+    assert!(matches!(tampered, Err(Error::Integrity { .. })));
+}
+
+#[tokio::test]
+async fn fetches_git_repositories() {
+    if !has_git() {
+        return;
+    }
+    // a repository with a tagged first commit and a second one on the default branch
+    let directory = tempfile::tempdir().unwrap();
+    let sources = tempfile::tempdir().unwrap();
+    let work = directory.path().join("work");
+    std::fs::create_dir_all(&work).unwrap();
+
+    git(&work, &["init", "--quiet"]);
+    write(
+        work.join("package.json"),
+        json!({ "name": "repo", "version": "1.0.0" }).to_string(),
+    );
+    git(&work, &["add", "-A"]);
+    git(&work, &["commit", "--quiet", "-m", "first"]);
+    git(&work, &["tag", "v1"]);
+    let first = git(&work, &["rev-parse", "HEAD"]);
+
+    write(
+        work.join("package.json"),
+        json!({ "name": "repo", "version": "2.0.0" }).to_string(),
+    );
+    git(&work, &["commit", "--quiet", "-am", "second"]);
+    let second = git(&work, &["rev-parse", "HEAD"]);
+
+    git(
+        directory.path(),
+        &["clone", "--quiet", "--bare", "work", "repo.git"],
+    );
+    let url = file_url(&directory.path().join("repo.git"));
+
+    let tagged = Specifier::parse(&format!("git+{}#v1", url));
+    let default_branch = Specifier::parse(&format!("git+{}", url));
+
+    // both commits are fetched at once, and have to end up in checkouts of their own
+    let (tagged, default_branch) = tokio::join!(
+        fetch_source(&tagged, "", directory.path(), sources.path()),
+        fetch_source(&default_branch, "", directory.path(), sources.path()),
+    );
+    let (tagged, default_branch) = (tagged.unwrap(), default_branch.unwrap());
+
+    assert_eq!(tagged.resolved, format!("git+{}#{}", url, first));
+    assert_eq!(tagged.manifest["version"], "1.0.0");
+    assert_eq!(default_branch.resolved, format!("git+{}#{}", url, second));
+    assert_eq!(default_branch.manifest["version"], "2.0.0");
+    assert_ne!(tagged.directory, default_branch.directory);
+    assert!(!tagged.directory.join(".git").exists());
+
+    // a locked commit is served from its checkout
+    let locked = fetch_source(
+        &Specifier::parse(&tagged.resolved),
+        "",
+        directory.path(),
+        sources.path(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(locked.directory, tagged.directory);
+    assert_eq!(locked.manifest["version"], "1.0.0");
+
+    let missing = fetch_source(
+        &Specifier::parse(&format!("git+{}#no-such-branch", url)),
+        "",
+        directory.path(),
+        sources.path(),
+    )
+    .await;
+    assert!(matches!(missing, Err(Error::Git { .. })));
+}
+
+#[test]
+fn unpacks_nothing_outside_of_the_destination() {
+    let directory = tempfile::tempdir().unwrap();
+    let outside = directory.path().join("outside");
+    let destination = directory.path().join("destination");
+    write(outside.join("file"), "outside");
+
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut append = |path: &str, entry_type: tar::EntryType, link: Option<&Path>, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        if let Some(link) = link {
+            header.set_link_name(link).unwrap();
+        }
+        header.set_cksum();
+        builder.append_data(&mut header, path, data).unwrap();
+    };
+
+    append(
+        "package/package.json",
+        tar::EntryType::Regular,
+        None,
+        br#"{"name":"evil","version":"1.0.0"}"#,
+    );
+    // a symlink out of the package, followed by a file written through it
+    append(
+        "package/escape",
+        tar::EntryType::Symlink,
+        Some(&outside),
+        b"",
+    );
+    append(
+        "package/escape/pwned",
+        tar::EntryType::Regular,
+        None,
+        b"pwned",
+    );
+    // a hardlink to a file outside of the package, followed by a write to it
+    append(
+        "package/hardlink",
+        tar::EntryType::Link,
+        Some(&outside.join("file")),
+        b"",
+    );
+    append("package/hardlink", tar::EntryType::Regular, None, b"pwned");
+
+    let tarball = builder.into_inner().unwrap().finish().unwrap();
+
+    unpack_tarball(tarball.as_slice(), &destination).unwrap();
+
+    assert!(destination.join("package.json").is_file());
+    assert!(!outside.join("pwned").exists());
+    assert_eq!(
+        std::fs::read_to_string(outside.join("file")).unwrap(),
+        "outside"
+    );
+    assert!(!destination
+        .join("escape")
+        .symlink_metadata()
+        .map_or(false, |metadata| metadata.file_type().is_symlink()));
+}
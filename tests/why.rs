@@ -0,0 +1,36 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use blaze::lockfile::Lockfile;
+
+fn why(root: &Path, package: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_blaze"))
+        .current_dir(root)
+        .args(["why", package])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn fails_when_the_package_is_not_installed() {
+    let root = tempfile::tempdir().unwrap();
+    std::fs::write(root.path().join("package.json"), "{}").unwrap();
+
+    let output = why(root.path(), "debug");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("No blaze.lock found"), "{}", stdout);
+    assert!(stdout.contains("Run `blaze install` first"), "{}", stdout);
+
+    Lockfile::default().write(root.path()).unwrap();
+
+    let output = why(root.path(), "debug");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout.contains("debug is not part of the dependency tree"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Run `blaze install` first"), "{}", stdout);
+}